@@ -0,0 +1,304 @@
+use crate::cli::{self, BisectArgs};
+use crate::diagnosis::plugins::Plugin;
+use crate::store;
+use crate::wp::WpCli;
+use std::fs;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use anyhow::Result;
+use console::style;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// What counts as "broken" while bisecting.
+enum Probe {
+    Url { url: String, client: reqwest::blocking::Client },
+    Eval(String),
+}
+
+impl Probe {
+    fn describe(&self) -> String {
+        match self {
+            Probe::Url { url, .. } => format!("{} returns a 5xx or cannot be fetched", url),
+            Probe::Eval(code) => format!("`wp eval {:?}` fails", code),
+        }
+    }
+
+    /// Returns why the probe failed, or `None` when the site passes.
+    fn check(&self, wp: &WpCli, root: &Path) -> Option<String> {
+        match self {
+            Probe::Url { url, client } => match client.get(url).send() {
+                Ok(resp) if resp.status().is_server_error() => Some(format!("{} returned {}", url, resp.status())),
+                Ok(_) => None,
+                Err(e) => Some(format!("{} could not be fetched: {}", url, e)),
+            },
+            Probe::Eval(code) => match wp.run_unchecked(&["eval", code], root) {
+                Ok(output) if output.status.success() => None,
+                Ok(output) => {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    let stdout = String::from_utf8_lossy(&output.stdout);
+                    let reason = stderr.lines().chain(stdout.lines()).find(|l| !l.trim().is_empty()).unwrap_or("");
+                    Some(format!("wp eval exited with {}: {}", output.status, reason.trim()))
+                }
+                Err(e) => Some(format!("wp eval failed: {}", e)),
+            },
+        }
+    }
+}
+
+/// The activation state to go back to, kept on disk in case the process is killed.
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    root: PathBuf,
+    active: Vec<String>,
+}
+
+/// Switches plugins on and off during the bisection and reactivates the
+/// original set when done, including on error or panic.
+struct Activation<'a> {
+    wp: &'a WpCli,
+    root: &'a Path,
+    original: Vec<String>,
+    current: Vec<String>,
+    state_file: PathBuf,
+    restored: bool,
+}
+
+impl<'a> Activation<'a> {
+    fn begin(wp: &'a WpCli, root: &'a Path, original: Vec<String>) -> Result<Self> {
+        let state_file = state_file(root)?;
+        let state = SavedState { root: root.to_path_buf(), active: original.clone() };
+        fs::write(&state_file, serde_json::to_string_pretty(&state)?)?;
+
+        Ok(Activation { wp, root, current: original.clone(), original, state_file, restored: false })
+    }
+
+    /// Leaves exactly `active` (a subset of the original plugins) active.
+    fn set(&mut self, active: &[String]) -> Result<()> {
+        // Skip loading plugins so a fataling plugin cannot stop WP-CLI itself
+        let off: Vec<String> = self.current.iter().filter(|p| !active.contains(p)).cloned().collect();
+        if !off.is_empty() {
+            toggle(self.wp, self.root, "deactivate", &off)?;
+            self.current.retain(|p| !off.contains(p));
+        }
+        let on: Vec<String> = active.iter().filter(|p| !self.current.contains(p)).cloned().collect();
+        if !on.is_empty() {
+            toggle(self.wp, self.root, "activate", &on)?;
+            self.current.extend(on);
+        }
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<()> {
+        // Activating an already active plugin is a no-op, so do not trust `current` here
+        toggle(self.wp, self.root, "activate", &self.original)?;
+        self.current = self.original.clone();
+        self.restored = true;
+        let _ = fs::remove_file(&self.state_file);
+        Ok(())
+    }
+}
+
+impl Drop for Activation<'_> {
+    fn drop(&mut self) {
+        if !self.restored {
+            if let Err(e) = self.restore() {
+                eprintln!("{} Could not restore the plugin activation state: {}", style("✘").red(), e);
+                eprintln!("  Reactivate manually: {}", activate_command(self.root, &self.original));
+            }
+        }
+    }
+}
+
+fn toggle(wp: &WpCli, root: &Path, action: &str, plugins: &[String]) -> Result<()> {
+    let mut args = vec!["plugin", action];
+    args.extend(plugins.iter().map(|p| p.as_str()));
+    args.extend(["--skip-plugins", "--skip-themes"]);
+    wp.run(&args, root)?;
+    Ok(())
+}
+
+/// One state file per site, so an interrupted run on one site is not overwritten by a run on another.
+fn state_file(root: &Path) -> Result<PathBuf> {
+    let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let hash = format!("{:x}", Sha256::digest(root.as_os_str().as_bytes()));
+    Ok(store::subdir("bisect")?.join(format!("state-{}.json", &hash[..16])))
+}
+
+fn activate_command(root: &Path, plugins: &[String]) -> String {
+    format!("wp plugin activate {} --path={}", plugins.join(" "), root.display())
+}
+
+pub fn run(wp: &WpCli, root: &Path, args: &BisectArgs, assume_yes: bool) -> Result<()> {
+    recover_interrupted(wp, root, assume_yes)?;
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let probe = match (&args.url, &args.eval) {
+        (Some(url), _) if url.starts_with("http://") || url.starts_with("https://") => {
+            Probe::Url { url: url.clone(), client }
+        }
+        (Some(path), _) => {
+            let home = wp.run(&["option", "get", "home"], root)?.trim().trim_end_matches('/').to_string();
+            Probe::Url { url: format!("{}/{}", home, path.trim_start_matches('/')), client }
+        }
+        (None, Some(code)) => Probe::Eval(code.clone()),
+        (None, None) => unreachable!("clap requires --url or --eval"),
+    };
+
+    println!("\n    > Fetching plugin list...");
+    let output = wp.run(&["plugin", "list", "--format=json"], root)?;
+    let plugins: Vec<Plugin> = serde_json::from_str(&output)?;
+    let active: Vec<String> = plugins.into_iter().filter(|p| p.status == "active").map(|p| p.name).collect();
+    if active.is_empty() {
+        println!("\n{}", style("No active plugins, nothing to bisect.").yellow());
+        return Ok(());
+    }
+
+    println!("\n{}", style("=== Bisect Plan ===").bold().underlined());
+    println!("Failure: {}", probe.describe());
+    println!("Plugins: {} active", active.len());
+    println!("Plugins are deactivated and reactivated in halves (about {} probe runs).", 2 + 2 * log2_ceil(active.len()));
+    println!("The original activation state is restored at the end, also on error or Ctrl-C.");
+    if !cli::confirm("Start bisecting? The site will be partially broken while this runs.", assume_yes)? {
+        println!("Bisect cancelled.");
+        return Ok(());
+    }
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = interrupted.clone();
+    ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst))?;
+
+    let mut activation = Activation::begin(wp, root, active.clone())?;
+    let mut fails = |set: &[String]| -> Result<bool> {
+        if interrupted.load(Ordering::SeqCst) {
+            return Err(anyhow::anyhow!("Interrupted"));
+        }
+        print!("    > Testing with {} of {} plugins active... ", set.len(), active.len());
+        std::io::stdout().flush()?;
+        activation.set(set)?;
+        let failure = probe.check(wp, root);
+        match &failure {
+            Some(reason) => println!("{} fails ({})", style("✘").red(), reason),
+            None => println!("{} passes", style("✔").green()),
+        }
+        Ok(failure.is_some())
+    };
+
+    let result = if !fails(&active)? {
+        Ok(None)
+    } else if fails(&[])? {
+        Err("The probe still fails with every plugin deactivated, so the cause is not a plugin (check the theme, mu-plugins and core).")
+    } else {
+        Ok(Some(find_culprits(&active, &mut fails)?))
+    };
+
+    println!("\n    > Restoring original plugin activation...");
+    activation.restore()?;
+
+    println!("\n{}", style("=== Bisect Result ===").bold().underlined());
+    match result {
+        Ok(None) => println!("{}", style("The probe passes with all plugins active, nothing to bisect.").green()),
+        Ok(Some(culprits)) if culprits.len() == 1 => {
+            println!("{} Culprit: {}", style("✘").red(), style(&culprits[0]).bold());
+        }
+        Ok(Some(culprits)) => {
+            println!("{} The failure needs all of these plugins active together:", style("✘").red());
+            for plugin in &culprits {
+                println!(" - {}", style(plugin).bold());
+            }
+        }
+        Err(message) => println!("{}", style(message).yellow()),
+    }
+    Ok(())
+}
+
+/// Reactivates the plugins of a bisect run that was killed before it could clean up.
+fn recover_interrupted(wp: &WpCli, root: &Path, assume_yes: bool) -> Result<()> {
+    let path = state_file(root)?;
+    if !path.is_file() {
+        return Ok(());
+    }
+    let state: SavedState = serde_json::from_slice(&fs::read(&path)?)?;
+
+    println!("{}", style("A previous bisect run did not finish restoring the active plugins.").yellow());
+    if !cli::confirm(&format!("Reactivate its {} plugins first?", state.active.len()), assume_yes)? {
+        return Err(anyhow::anyhow!("Reactivate them before bisecting again: {}", activate_command(root, &state.active)));
+    }
+    toggle(wp, root, "activate", &state.active)?;
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+fn log2_ceil(n: usize) -> usize {
+    (usize::BITS - n.saturating_sub(1).leading_zeros()) as usize
+}
+
+/// Finds a minimal set of plugins that still makes `fails` true.
+///
+/// Expects `fails(plugins)` to be true and `fails(&[])` to be false. Each round
+/// binary searches for the shortest failing prefix of the remaining plugins; the
+/// last plugin of that prefix is needed for the failure, and the search repeats
+/// on what is left before it until the needed plugins fail on their own.
+fn find_culprits(plugins: &[String], fails: &mut impl FnMut(&[String]) -> Result<bool>) -> Result<Vec<String>> {
+    let mut required: Vec<String> = Vec::new();
+    let mut pool = plugins.to_vec();
+
+    loop {
+        if pool.is_empty() || (!required.is_empty() && fails(&required)?) {
+            return Ok(required);
+        }
+
+        // fails(required + pool[..hi]) holds, fails(required + pool[..lo]) does not
+        let (mut lo, mut hi) = (0, pool.len());
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            if fails(&[required.as_slice(), &pool[..mid]].concat())? {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        required.push(pool[hi - 1].clone());
+        pool.truncate(hi - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("plugin-{}", i)).collect()
+    }
+
+    #[test]
+    fn test_find_single_culprit() -> Result<()> {
+        let plugins = names(16);
+        let mut runs = 0;
+        let culprits = find_culprits(&plugins, &mut |set: &[String]| {
+            runs += 1;
+            Ok(set.contains(&"plugin-11".to_string()))
+        })?;
+
+        assert_eq!(culprits, vec!["plugin-11".to_string()]);
+        assert!(runs <= 5, "took {} runs", runs);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_conflicting_pair() -> Result<()> {
+        let plugins = names(10);
+        let culprits = find_culprits(&plugins, &mut |set: &[String]| {
+            Ok(set.contains(&"plugin-2".to_string()) && set.contains(&"plugin-7".to_string()))
+        })?;
+
+        assert_eq!(culprits, vec!["plugin-7".to_string(), "plugin-2".to_string()]);
+        Ok(())
+    }
+}
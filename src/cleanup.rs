@@ -0,0 +1,236 @@
+use crate::cli::{self, CleanupArgs};
+use crate::store;
+use crate::wp::WpCli;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+use chrono::{Duration, NaiveDateTime, Utc};
+use console::style;
+use serde::{Deserialize, Serialize};
+
+/// How many IDs are passed to a single `wp ... delete` call.
+const BATCH_SIZE: usize = 100;
+
+#[derive(Deserialize, Debug)]
+struct Post {
+    #[serde(rename = "ID")]
+    id: u64,
+    #[serde(default)]
+    post_parent: u64,
+    post_modified_gmt: String,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+enum Kind {
+    Revision,
+    TrashedPost,
+    AutoDraft,
+    SpamComment,
+    TrashedComment,
+}
+
+impl Kind {
+    fn label(&self) -> &'static str {
+        match self {
+            Kind::Revision => "excess post revisions",
+            Kind::TrashedPost => "trashed posts",
+            Kind::AutoDraft => "stale auto-drafts",
+            Kind::SpamComment => "spam comments",
+            Kind::TrashedComment => "trashed comments",
+        }
+    }
+
+    fn object(&self) -> &'static str {
+        match self {
+            Kind::SpamComment | Kind::TrashedComment => "comment",
+            _ => "post",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct Batch {
+    kind: Kind,
+    ids: Vec<u64>,
+}
+
+#[derive(Serialize, Debug)]
+struct CleanupRecord {
+    run_id: String,
+    root: String,
+    deleted: Vec<Batch>,
+    failed: Vec<Batch>,
+}
+
+pub fn run(wp: &WpCli, root: &Path, args: &CleanupArgs, assume_yes: bool) -> Result<()> {
+    println!("\nBuilding cleanup plan...");
+    let now = Utc::now().naive_utc();
+    let mut plan = Vec::new();
+
+    println!("    > Listing post revisions...");
+    // Revisions have the `inherit` status, which the default status filter leaves out
+    let output = wp.run(&["post", "list", "--post_type=revision", "--post_status=inherit", "--posts_per_page=-1",
+        "--fields=ID,post_parent,post_modified_gmt", "--format=json"], root)?;
+    plan.push(Batch { kind: Kind::Revision, ids: excess_revisions(&parse_posts(&output)?, args.keep_revisions) });
+
+    println!("    > Listing trashed posts...");
+    let output = wp.run(&["post", "list", "--post_type=any", "--post_status=trash", "--posts_per_page=-1",
+        "--fields=ID,post_modified_gmt", "--format=json"], root)?;
+    let cutoff = now - Duration::days(args.trash_days);
+    plan.push(Batch { kind: Kind::TrashedPost, ids: older_than(&parse_posts(&output)?, cutoff) });
+
+    println!("    > Listing auto-drafts...");
+    let output = wp.run(&["post", "list", "--post_type=any", "--post_status=auto-draft", "--posts_per_page=-1",
+        "--fields=ID,post_modified_gmt", "--format=json"], root)?;
+    let cutoff = now - Duration::days(args.auto_draft_days);
+    plan.push(Batch { kind: Kind::AutoDraft, ids: older_than(&parse_posts(&output)?, cutoff) });
+
+    println!("    > Listing spam and trashed comments...");
+    let output = wp.run(&["comment", "list", "--status=spam", "--format=ids"], root)?;
+    plan.push(Batch { kind: Kind::SpamComment, ids: parse_ids(&output) });
+    let output = wp.run(&["comment", "list", "--status=trash", "--format=ids"], root)?;
+    plan.push(Batch { kind: Kind::TrashedComment, ids: parse_ids(&output) });
+
+    print_plan(&plan, args);
+
+    let total: usize = plan.iter().map(|b| b.ids.len()).sum();
+    if total == 0 {
+        println!("\n{}", style("Nothing to clean up.").green().bold());
+        return Ok(());
+    }
+    if args.dry_run {
+        println!("\n{}", style("Dry run: nothing was deleted.").yellow());
+        return Ok(());
+    }
+    if !cli::confirm(&format!("Permanently delete these {} items?", total), assume_yes)? {
+        println!("Cleanup cancelled.");
+        return Ok(());
+    }
+
+    let mut record = CleanupRecord {
+        run_id: store::timestamp_id(),
+        root: root.display().to_string(),
+        deleted: Vec::new(),
+        failed: Vec::new(),
+    };
+
+    for batch in plan.into_iter().filter(|b| !b.ids.is_empty()) {
+        println!("    > Deleting {} {}...", batch.ids.len(), batch.kind.label());
+        for chunk in batch.ids.chunks(BATCH_SIZE) {
+            let ids: Vec<String> = chunk.iter().map(|id| id.to_string()).collect();
+            let mut cmd = vec![batch.kind.object(), "delete"];
+            cmd.extend(ids.iter().map(|s| s.as_str()));
+            cmd.push("--force");
+
+            let target = match wp.run(&cmd, root) {
+                Ok(_) => &mut record.deleted,
+                Err(e) => {
+                    eprintln!("{} {}", style("Error:").red(), e);
+                    &mut record.failed
+                }
+            };
+            target.push(Batch { kind: batch.kind, ids: chunk.to_vec() });
+        }
+    }
+
+    let log_path = store::subdir("cleanup")?.join(format!("{}.json", record.run_id));
+    fs::write(&log_path, serde_json::to_string_pretty(&record)?)?;
+
+    let deleted: usize = record.deleted.iter().map(|b| b.ids.len()).sum();
+    println!("\n{} Deleted {} items. Log written to {:?}", style("✔").green(), deleted, log_path);
+    if !record.failed.is_empty() {
+        println!("{}", style("Some deletions failed, see the log for the affected IDs.").yellow());
+    }
+
+    Ok(())
+}
+
+fn print_plan(plan: &[Batch], args: &CleanupArgs) {
+    println!("\n{}", style("=== Cleanup Plan ===").bold().underlined());
+    for batch in plan {
+        let rule = match batch.kind {
+            Kind::Revision => format!("keeping the newest {} per post", args.keep_revisions),
+            Kind::TrashedPost => format!("in trash for more than {} days", args.trash_days),
+            Kind::AutoDraft => format!("older than {} days", args.auto_draft_days),
+            Kind::SpamComment | Kind::TrashedComment => "all".to_string(),
+        };
+        println!(" - {:>6} {} ({})", batch.ids.len(), batch.kind.label(), rule);
+    }
+}
+
+fn parse_posts(json_output: &str) -> Result<Vec<Post>> {
+    if json_output.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_str(json_output)?)
+}
+
+fn parse_ids(output: &str) -> Vec<u64> {
+    output.split_whitespace().filter_map(|id| id.parse().ok()).collect()
+}
+
+fn parse_gmt(date: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(date.trim(), "%Y-%m-%d %H:%M:%S").ok()
+}
+
+/// Returns the revisions beyond the newest `keep` of each parent post.
+fn excess_revisions(revisions: &[Post], keep: usize) -> Vec<u64> {
+    let mut by_parent: HashMap<u64, Vec<&Post>> = HashMap::new();
+    for revision in revisions {
+        by_parent.entry(revision.post_parent).or_default().push(revision);
+    }
+
+    let mut excess = Vec::new();
+    for group in by_parent.values_mut() {
+        group.sort_by(|a, b| b.post_modified_gmt.cmp(&a.post_modified_gmt).then(b.id.cmp(&a.id)));
+        excess.extend(group.iter().skip(keep).map(|p| p.id));
+    }
+    excess.sort_unstable();
+    excess
+}
+
+/// Returns the posts last modified before `cutoff`. Posts without a usable
+/// date are never selected.
+fn older_than(posts: &[Post], cutoff: NaiveDateTime) -> Vec<u64> {
+    posts.iter()
+        .filter(|p| parse_gmt(&p.post_modified_gmt).is_some_and(|d| d < cutoff))
+        .map(|p| p.id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excess_revisions_keeps_newest_per_post() -> Result<()> {
+        let json = r#"[
+            {"ID": 11, "post_parent": 1, "post_modified_gmt": "2024-01-01 10:00:00"},
+            {"ID": 12, "post_parent": 1, "post_modified_gmt": "2024-01-02 10:00:00"},
+            {"ID": 13, "post_parent": 1, "post_modified_gmt": "2024-01-03 10:00:00"},
+            {"ID": 21, "post_parent": 2, "post_modified_gmt": "2024-01-01 10:00:00"}
+        ]"#;
+
+        let revisions = parse_posts(json)?;
+
+        assert_eq!(excess_revisions(&revisions, 2), vec![11]);
+        assert_eq!(excess_revisions(&revisions, 0), vec![11, 12, 13, 21]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_older_than_skips_recent_and_undated() -> Result<()> {
+        let json = r#"[
+            {"ID": 1, "post_modified_gmt": "2024-01-01 00:00:00"},
+            {"ID": 2, "post_modified_gmt": "2024-03-01 00:00:00"},
+            {"ID": 3, "post_modified_gmt": "0000-00-00 00:00:00"}
+        ]"#;
+        let cutoff = parse_gmt("2024-02-01 00:00:00").unwrap();
+
+        assert_eq!(older_than(&parse_posts(json)?, cutoff), vec![1]);
+        assert_eq!(parse_ids("4 5  6\n"), vec![4, 5, 6]);
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -6,4 +7,173 @@ pub struct Cli {
     /// Turn debugging information on
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub debug: u8,
+
+    /// Answer "yes" to every confirmation prompt
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Remove excess revisions, old trash, spam comments and stale auto-drafts
+    Cleanup(CleanupArgs),
+    /// Back up the database and key files into a timestamped archive
+    Snapshot(SnapshotArgs),
+    /// Verify and restore a snapshot
+    Restore(RestoreArgs),
+    /// Apply the remediations offered by the diagnosis modules
+    Fix(FixArgs),
+    /// Revert the changes made by a previous `fix` run
+    Undo(UndoArgs),
+    /// Update plugins one at a time, rolling back any update that breaks the site
+    Update(UpdateArgs),
+    /// Show a diff of modified core files against the pristine WordPress release
+    CoreDiff(CoreDiffArgs),
+    /// Manage the offline vulnerability database
+    Vulndb(VulndbArgs),
+    /// Find the plugin(s) causing a failure by deactivating halves of the active plugins
+    Bisect(BisectArgs),
+    /// Move suspicious files out of the web root, or put them back
+    Quarantine(QuarantineArgs),
+}
+
+#[derive(Args)]
+pub struct CleanupArgs {
+    /// Number of revisions to keep for each post
+    #[arg(long, default_value_t = 5)]
+    pub keep_revisions: usize,
+
+    /// Delete trashed posts older than this many days
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(i64).range(0..=36500))]
+    pub trash_days: i64,
+
+    /// Delete auto-drafts older than this many days
+    #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(i64).range(0..=36500))]
+    pub auto_draft_days: i64,
+
+    /// Only print the cleanup plan, do not delete anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct SnapshotArgs {
+    /// Path to include, relative to the WordPress root (repeatable).
+    /// Defaults to wp-config.php, plugins, themes and mu-plugins
+    #[arg(long = "path")]
+    pub paths: Vec<String>,
+
+    /// Do not export the database
+    #[arg(long)]
+    pub no_database: bool,
+}
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// Snapshot id (e.g. 20240131-154502) or path to a snapshot archive
+    pub snapshot: String,
+
+    /// Restore files only, leave the database untouched
+    #[arg(long)]
+    pub files_only: bool,
+}
+
+#[derive(Args)]
+pub struct FixArgs {
+    /// Only preview the available fixes, do not change anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct UndoArgs {
+    /// Run id printed by `wp-agent fix` (e.g. 20240131-154502)
+    pub run_id: String,
+}
+
+#[derive(Args)]
+pub struct UpdateArgs {
+    /// Plugin slugs to update. Defaults to every plugin with an update available
+    pub plugins: Vec<String>,
+
+    /// Only print the update plan, do not update anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct CoreDiffArgs {
+    /// Core files to diff, relative to the WordPress root. Defaults to every modified core file
+    pub files: Vec<String>,
+
+    /// WordPress .tar.gz release to compare against. Defaults to WP-CLI's download cache
+    #[arg(long)]
+    pub archive: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct VulndbArgs {
+    #[command(subcommand)]
+    pub command: VulndbCommand,
+}
+
+#[derive(Subcommand)]
+pub enum VulndbCommand {
+    /// Import a Wordfence Intelligence feed or OSV records from a local file
+    Import {
+        file: PathBuf,
+    },
+    /// Show what is in the imported database
+    Status,
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("probe").required(true).args(["url", "eval"])))]
+pub struct BisectArgs {
+    /// URL, or path relative to the home URL, that returns a 5xx while the problem occurs
+    #[arg(long)]
+    pub url: Option<String>,
+
+    /// PHP code for `wp eval` that fails while the problem occurs
+    #[arg(long)]
+    pub eval: Option<String>,
+}
+
+#[derive(Args)]
+pub struct QuarantineArgs {
+    #[command(subcommand)]
+    pub command: QuarantineCommand,
+}
+
+#[derive(Subcommand)]
+pub enum QuarantineCommand {
+    /// Quarantine files. Without files, choose from the files flagged by the malware scan and core checksums
+    Add {
+        /// Paths relative to the WordPress root
+        files: Vec<String>,
+    },
+    /// List quarantined files
+    List,
+    /// Restore files from a quarantine run (e.g. after a false positive)
+    Restore {
+        /// Quarantine id printed by `wp-agent quarantine add` (e.g. 20240131-154502)
+        id: String,
+        /// Only restore these paths. Defaults to every file of the run
+        files: Vec<String>,
+    },
+}
+
+/// Asks the user to confirm an action, unless `--yes` was given.
+pub fn confirm(prompt: &str, assume_yes: bool) -> anyhow::Result<bool> {
+    if assume_yes {
+        return Ok(true);
+    }
+
+    Ok(dialoguer::Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()?)
 }
@@ -0,0 +1,152 @@
+use crate::cli::CoreDiffArgs;
+use crate::diagnosis::checksums::{self, FileIssue};
+use crate::wp::WpCli;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use console::style;
+use flate2::read::GzDecoder;
+use similar::TextDiff;
+
+pub fn run(wp: &WpCli, root: &Path, args: &CoreDiffArgs) -> Result<()> {
+    let version = wp.run(&["core", "version"], root)?.trim().to_string();
+    let archive = match &args.archive {
+        Some(path) => path.clone(),
+        None => find_cached_archive(&version)?,
+    };
+    println!("Comparing against {:?} (WordPress {})", archive, version);
+
+    let files = if args.files.is_empty() {
+        println!("    > Verifying core checksums...");
+        let output = wp.run_unchecked(&["core", "verify-checksums"], root)?;
+        checksums::parse_core_checksums(&String::from_utf8_lossy(&output.stderr))
+            .into_iter()
+            .filter(|f| f.issue == FileIssue::Modified)
+            .map(|f| f.file)
+            .collect()
+    } else {
+        args.files.clone()
+    };
+
+    if files.is_empty() {
+        println!("\n{}", style("No modified core files.").green().bold());
+        return Ok(());
+    }
+
+    let mut pristine_files = read_from_archive(&archive, &files)?;
+    for file in &files {
+        let current = String::from_utf8_lossy(&fs::read(root.join(file))?).to_string();
+        let pristine = match pristine_files.remove(file) {
+            Some(content) => content,
+            None => {
+                println!("\n{} {} is not part of WordPress {}", style("?").yellow(), file, version);
+                continue;
+            }
+        };
+        print_diff(file, &pristine, &current);
+    }
+    Ok(())
+}
+
+/// Looks for the archive WP-CLI caches when it downloads core, e.g.
+/// `~/.wp-cli/cache/core/wordpress-6.4.2-en_US.tar.gz`.
+pub fn find_cached_archive(version: &str) -> Result<PathBuf> {
+    let cache_dir = match std::env::var_os("WP_CLI_CACHE_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(".wp-cli/cache"),
+    }
+    .join("core");
+
+    let prefix = format!("wordpress-{}-", version);
+    let mut candidates: Vec<PathBuf> = fs::read_dir(&cache_dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    candidates.retain(|p| {
+        let name = p.file_name().unwrap_or_default().to_string_lossy();
+        name.starts_with(&prefix) && name.ends_with(".tar.gz")
+    });
+    // Core PHP files are the same in every locale, but prefer the default one
+    candidates.sort_by_key(|p| !p.to_string_lossy().ends_with("-en_US.tar.gz"));
+
+    candidates.into_iter().next().ok_or_else(|| anyhow::anyhow!(
+        "No cached WordPress {} archive in {:?}. Run `wp core download --version={} --path=/tmp/wp-{}` to cache it, or pass --archive.",
+        version, cache_dir, version, version
+    ))
+}
+
+/// Reads `files` (relative to the WordPress root) from a core archive in a single pass.
+/// Files the archive does not contain are left out of the result.
+fn read_from_archive(archive: &Path, files: &[String]) -> Result<HashMap<String, String>> {
+    let mut wanted: HashMap<PathBuf, &String> = files.iter().map(|f| (Path::new("wordpress").join(f), f)).collect();
+    let mut found = HashMap::new();
+    let mut tar = tar::Archive::new(GzDecoder::new(fs::File::open(archive)?));
+
+    for entry in tar.entries()? {
+        if wanted.is_empty() {
+            break;
+        }
+        let mut entry = entry?;
+        if let Some(file) = wanted.remove(entry.path()?.as_ref()) {
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            found.insert(file.clone(), String::from_utf8_lossy(&content).to_string());
+        }
+    }
+    Ok(found)
+}
+
+fn unified_diff(file: &str, pristine: &str, current: &str) -> String {
+    TextDiff::from_lines(pristine, current)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{} (pristine)", file), &format!("b/{}", file))
+        .to_string()
+}
+
+fn print_diff(file: &str, pristine: &str, current: &str) {
+    println!();
+    for line in unified_diff(file, pristine, current).lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            println!("{}", style(line).bold());
+        } else if line.starts_with('+') {
+            println!("{}", style(line).green());
+        } else if line.starts_with('-') {
+            println!("{}", style(line).red());
+        } else if line.starts_with("@@") {
+            println!("{}", style(line).cyan());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_from_archive_and_diff() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join("wordpress-6.4.2-en_US.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            fs::File::create(&archive)?, flate2::Compression::default()));
+        let content = b"<?php\n$wp_version = '6.4.2';\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "wordpress/wp-includes/version.php", &content[..])?;
+        builder.into_inner()?.finish()?;
+
+        let files = vec!["wp-includes/version.php".to_string(), "wp-includes/missing.php".to_string()];
+        let mut found = read_from_archive(&archive, &files)?;
+        assert_eq!(found.len(), 1);
+        let pristine = found.remove("wp-includes/version.php").unwrap();
+
+        let diff = unified_diff("wp-includes/version.php", &pristine, "<?php\n$wp_version = '6.4.2';\neval($_POST['x']);\n");
+        assert!(diff.contains("+++ b/wp-includes/version.php"));
+        assert!(diff.contains("+eval($_POST['x']);"));
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use anyhow::Result;
+use serde::Deserialize;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FileIssue {
+    Modified,
+    Added,
+    Missing,
+    Other(String),
+}
+
+impl FileIssue {
+    pub fn label(&self) -> &str {
+        match self {
+            FileIssue::Modified => "modified",
+            FileIssue::Added => "added",
+            FileIssue::Missing => "missing",
+            FileIssue::Other(message) => message,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FileFinding {
+    pub issue: FileIssue,
+    pub file: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct PluginChecksums {
+    /// Plugins whose files do not match wordpress.org, keyed by slug.
+    pub failed: BTreeMap<String, Vec<FileFinding>>,
+    /// Plugins wordpress.org has no checksums for (premium, custom or unknown versions).
+    pub unverifiable: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PluginRow {
+    plugin_name: String,
+    file: String,
+    message: String,
+}
+
+/// Parses `wp plugin verify-checksums --all --format=json`.
+/// Mismatches are printed as JSON on stdout, skipped plugins as warnings on stderr.
+pub fn parse_plugin_checksums(stdout: &str, stderr: &str) -> Result<PluginChecksums> {
+    let mut result = PluginChecksums::default();
+
+    for line in stdout.lines().filter(|l| l.trim_start().starts_with('[')) {
+        let rows: Vec<PluginRow> = serde_json::from_str(line)?;
+        for row in rows {
+            let issue = match row.message.as_str() {
+                "Checksum does not match" => FileIssue::Modified,
+                "File was added" => FileIssue::Added,
+                "File is missing" => FileIssue::Missing,
+                other => FileIssue::Other(other.to_string()),
+            };
+            result.failed.entry(row.plugin_name).or_default().push(FileFinding { issue, file: row.file });
+        }
+    }
+
+    let mut errors = Vec::new();
+    for line in stderr.lines().map(|l| l.trim()) {
+        if line.starts_with("Warning:") && line.contains("skipping") {
+            // "Could not retrieve the checksums for version 1.0 of plugin foo, skipping."
+            if let Some((_, rest)) = line.rsplit_once(" plugin ") {
+                result.unverifiable.push(rest.trim_end_matches(", skipping.").to_string());
+            }
+        } else if line.starts_with("Error:") && !line.starts_with("Error: Only verified") {
+            errors.push(line.to_string());
+        }
+    }
+
+    if result.failed.is_empty() && result.unverifiable.is_empty() && !errors.is_empty() {
+        return Err(anyhow::anyhow!(errors.join(" ")));
+    }
+    Ok(result)
+}
+
+/// Parses the warnings printed by a failed `wp core verify-checksums`.
+pub fn parse_core_checksums(stderr: &str) -> Vec<FileFinding> {
+    let patterns = [
+        ("Warning: File doesn't verify against checksum: ", FileIssue::Modified),
+        ("Warning: File should not exist: ", FileIssue::Added),
+        ("Warning: File doesn't exist: ", FileIssue::Missing),
+    ];
+
+    stderr.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            patterns.iter().find_map(|(prefix, issue)| {
+                line.strip_prefix(prefix).map(|file| FileFinding { issue: issue.clone(), file: file.trim().to_string() })
+            })
+        })
+        .collect()
+}
+
+/// Unexpected PHP inside wp-admin or wp-includes is almost always a backdoor.
+pub fn is_high_severity(finding: &FileFinding) -> bool {
+    finding.issue == FileIssue::Added
+        && finding.file.ends_with(".php")
+        && (finding.file.starts_with("wp-admin/") || finding.file.starts_with("wp-includes/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plugin_checksums() -> Result<()> {
+        let stdout = r#"[{"plugin_name":"akismet","file":"akismet.php","message":"Checksum does not match"},{"plugin_name":"akismet","file":"shell.php","message":"File was added"},{"plugin_name":"hello-dolly","file":"hello.php","message":"File is missing"}]"#;
+        let stderr = "Warning: Could not retrieve the checksums for version 2.1 of plugin premium-forms, skipping.\n\
+                      Error: Only verified 0 of 3 plugins (2 failed, 1 skipped).";
+
+        let result = parse_plugin_checksums(stdout, stderr)?;
+
+        assert_eq!(result.failed["akismet"], vec![
+            FileFinding { issue: FileIssue::Modified, file: "akismet.php".to_string() },
+            FileFinding { issue: FileIssue::Added, file: "shell.php".to_string() },
+        ]);
+        assert_eq!(result.failed["hello-dolly"][0].issue, FileIssue::Missing);
+        assert_eq!(result.unverifiable, vec!["premium-forms"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_core_checksums() {
+        let stderr = "Warning: File doesn't verify against checksum: wp-includes/version.php\n\
+                      Warning: File should not exist: wp-admin/css/about.php\n\
+                      Warning: File should not exist: wp-content/readme.txt\n\
+                      Warning: File doesn't exist: wp-includes/load.php\n\
+                      Error: WordPress installation doesn't verify against checksums.";
+
+        let findings = parse_core_checksums(stderr);
+
+        assert_eq!(findings.len(), 4);
+        assert_eq!(findings[0], FileFinding { issue: FileIssue::Modified, file: "wp-includes/version.php".to_string() });
+        assert_eq!(findings[3].issue, FileIssue::Missing);
+        assert!(is_high_severity(&findings[1]));
+        assert!(!is_high_severity(&findings[2]));
+    }
+
+    #[test]
+    fn test_parse_plugin_checksums_clean_and_error() -> Result<()> {
+        assert_eq!(parse_plugin_checksums("Success: Verified 3 of 3 plugins.", "")?, PluginChecksums::default());
+        assert!(parse_plugin_checksums("", "Error: 'verify-checksums' is not a registered subcommand of 'plugin'.").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+use crate::diagnosis::Status;
+use crate::wp::WpCli;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde_json::Value;
+
+/// Events are only reported as overdue after this many minutes, spawn-on-visit cron is never exact.
+const OVERDUE_GRACE_MINUTES: i64 = 10;
+
+/// Overdue by more than this means cron is not running at all.
+const STALLED_HOURS: i64 = 1;
+
+/// Recurring schedules shorter than this run on most page loads.
+const FREQUENT_SECONDS: i64 = 300;
+
+/// Places a system cron job or systemd timer for WordPress would be defined.
+const RUNNER_PATHS: &[&str] = &["/etc/crontab", "/etc/cron.d", "/var/spool/cron", "/var/spool/cron/crontabs", "/etc/systemd/system"];
+
+#[derive(Debug, PartialEq)]
+pub struct CronEvent {
+    pub hook: String,
+    pub next_run: Option<DateTime<Utc>>,
+    /// Seconds between runs, 0 for single events.
+    pub interval: i64,
+    pub recurrence: String,
+}
+
+pub fn check(wp: &WpCli, root: &Path, status: &mut Status, details: &mut Vec<String>) {
+    let csv = match wp.run(&["cron", "event", "list", "--fields=hook,next_run_gmt,interval,recurrence", "--format=csv"], root) {
+        Ok(csv) => csv,
+        Err(_) => {
+            details.push("Could not check cron events.".to_string());
+            return;
+        }
+    };
+    let events = match parse_events(&csv) {
+        Ok(events) => events,
+        Err(e) => {
+            details.push(format!("Could not parse cron events: {}", e));
+            return;
+        }
+    };
+
+    let inactive: Vec<String> = wp.run(&["plugin", "list", "--status=inactive", "--field=name"], root)
+        .map(|out| out.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect())
+        .unwrap_or_default();
+    let cron_disabled = wp.run(&["config", "get", "DISABLE_WP_CRON", "--type=constant", "--format=json"], root)
+        .ok()
+        .and_then(|v| serde_json::from_str::<Value>(v.trim()).ok())
+        .is_some_and(|v| matches!(v, Value::Bool(true)) || v.as_i64().is_some_and(|n| n != 0) || v.as_str().is_some_and(|s| !s.is_empty() && s != "0"));
+    let runner = if cron_disabled { find_runner() } else { None };
+
+    analyze(&events, &inactive, Utc::now(), cron_disabled, runner.as_deref(), status, details);
+}
+
+/// Parses `wp cron event list --format=csv` output using its header row.
+pub fn parse_events(csv: &str) -> Result<Vec<CronEvent>> {
+    let mut lines = csv.lines().filter(|l| !l.trim().is_empty());
+    let header = split_csv_line(lines.next().unwrap_or_default());
+    let column = |name: &str| header.iter().position(|h| h == name)
+        .ok_or_else(|| anyhow::anyhow!("missing column '{}'", name));
+    let (hook, next_run, interval, recurrence) = (column("hook")?, column("next_run_gmt")?, column("interval")?, column("recurrence")?);
+
+    Ok(lines.map(split_csv_line).map(|row| {
+        let field = |i: usize| row.get(i).map(|s| s.as_str()).unwrap_or_default();
+        CronEvent {
+            hook: field(hook).to_string(),
+            next_run: NaiveDateTime::parse_from_str(field(next_run), "%Y-%m-%d %H:%M:%S").ok().map(|d| d.and_utc()),
+            interval: field(interval).parse().unwrap_or(0),
+            recurrence: field(recurrence).to_string(),
+        }
+    }).collect())
+}
+
+/// Splits one line as written by PHP's `fputcsv()`.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+pub fn analyze(events: &[CronEvent], inactive_plugins: &[String], now: DateTime<Utc>, cron_disabled: bool,
+    runner: Option<&str>, status: &mut Status, details: &mut Vec<String>) {
+    details.push(format!("Cron events scheduled: {}", events.len()));
+
+    // Overdue events
+    let mut overdue: Vec<(&CronEvent, Duration)> = events.iter()
+        .filter_map(|e| e.next_run.map(|next| (e, now - next)))
+        .filter(|(_, late)| *late > Duration::minutes(OVERDUE_GRACE_MINUTES))
+        .collect();
+    overdue.sort_by_key(|(_, late)| std::cmp::Reverse(*late));
+    let stalled = overdue.first().is_some_and(|(_, late)| *late > Duration::hours(STALLED_HOURS));
+    if !overdue.is_empty() {
+        if stalled {
+            status.escalate(Status::Warning);
+        }
+        details.push(format!("{}{} cron events are overdue:", if stalled { "Warning: " } else { "" }, overdue.len()));
+        for (event, late) in overdue.iter().take(5) {
+            details.push(format!(" - {} (overdue by {})", event.hook, human(*late)));
+        }
+    }
+
+    // The same recurring hook scheduled more than once usually means a plugin schedules it on every load
+    let mut recurring: BTreeMap<&str, usize> = BTreeMap::new();
+    for event in events.iter().filter(|e| e.interval > 0) {
+        *recurring.entry(&event.hook).or_default() += 1;
+    }
+    for (hook, count) in recurring.iter().filter(|(_, count)| **count > 1) {
+        status.escalate(Status::Warning);
+        details.push(format!("Warning: Recurring hook '{}' is scheduled {} times.", hook, count));
+    }
+
+    // Schedules that fire on nearly every request
+    let mut frequent: BTreeMap<&str, &CronEvent> = BTreeMap::new();
+    for event in events.iter().filter(|e| e.interval > 0 && e.interval < FREQUENT_SECONDS) {
+        frequent.entry(&event.hook).or_insert(event);
+    }
+    for event in frequent.into_values() {
+        status.escalate(Status::Warning);
+        details.push(format!("Warning: Hook '{}' runs every {} seconds ({}).", event.hook, event.interval, event.recurrence));
+    }
+
+    // Events left behind by plugins that are no longer active
+    let mut orphaned: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for event in events {
+        if let Some(plugin) = inactive_plugins.iter().find(|p| event.hook.starts_with(&p.replace('-', "_"))) {
+            let hooks = orphaned.entry(plugin).or_default();
+            if !hooks.contains(&event.hook.as_str()) {
+                hooks.push(&event.hook);
+            }
+        }
+    }
+    for (plugin, hooks) in orphaned {
+        details.push(format!("Note: Events from inactive plugin '{}': {}", plugin, hooks.join(", ")));
+    }
+
+    // DISABLE_WP_CRON needs something else to run wp-cron
+    if cron_disabled {
+        match runner {
+            Some(found) => details.push(format!("DISABLE_WP_CRON is set, cron is run by {}.", found)),
+            None if stalled => {
+                status.escalate(Status::Error);
+                details.push("Critical: DISABLE_WP_CRON is set, no system cron job or systemd timer runs wp-cron, and events are overdue.".to_string());
+            }
+            None => {
+                status.escalate(Status::Warning);
+                details.push("Warning: DISABLE_WP_CRON is set but no system cron job or systemd timer for wp-cron was found (an external service may run it).".to_string());
+            }
+        }
+    }
+}
+
+fn human(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    match (minutes / 1440, minutes % 1440 / 60, minutes % 60) {
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    }
+}
+
+/// Looks for a crontab line or systemd unit that runs WordPress cron.
+fn find_runner() -> Option<String> {
+    if let Ok(output) = Command::new("crontab").arg("-l").output() {
+        if runs_wp_cron(&String::from_utf8_lossy(&output.stdout)) {
+            return Some("the current user's crontab".to_string());
+        }
+    }
+
+    for base in RUNNER_PATHS {
+        let base = Path::new(base);
+        let files: Vec<_> = if base.is_dir() {
+            fs::read_dir(base).map(|d| d.filter_map(|e| e.ok()).map(|e| e.path()).collect()).unwrap_or_default()
+        } else {
+            vec![base.to_path_buf()]
+        };
+        for file in files.into_iter().filter(|f| f.is_file()) {
+            if fs::read_to_string(&file).is_ok_and(|content| runs_wp_cron(&content)) {
+                return Some(file.display().to_string());
+            }
+        }
+    }
+    None
+}
+
+fn runs_wp_cron(content: &str) -> bool {
+    content.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.starts_with('#'))
+        .any(|l| l.contains("wp-cron.php") || l.contains("cron event run"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_and_analyze_events() -> Result<()> {
+        let csv = "hook,next_run_gmt,interval,recurrence\n\
+            wp_version_check,\"2024-01-31 10:00:00\",43200,\"12 hours\"\n\
+            my_sync,\"2024-01-31 11:55:00\",60,\"1 minute\"\n\
+            my_sync,\"2024-01-31 11:56:00\",60,\"1 minute\"\n\
+            wordfence_daily_cron,\"2024-02-01 00:00:00\",86400,\"1 day\"\n\
+            publish_future_post,\"2024-02-03 09:00:00\",,Non-repeating\n";
+        let events = parse_events(csv)?;
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[4], CronEvent {
+            hook: "publish_future_post".to_string(),
+            next_run: Some(Utc.with_ymd_and_hms(2024, 2, 3, 9, 0, 0).unwrap()),
+            interval: 0,
+            recurrence: "Non-repeating".to_string(),
+        });
+
+        let now = Utc.with_ymd_and_hms(2024, 1, 31, 12, 0, 0).unwrap();
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        analyze(&events, &["wordfence".to_string()], now, true, None, &mut status, &mut details);
+
+        assert_eq!(status, Status::Error);
+        assert!(details.iter().any(|d| d == "Warning: 1 cron events are overdue:"));
+        assert!(details.iter().any(|d| d == " - wp_version_check (overdue by 2h 0m)"));
+        assert!(details.iter().any(|d| d == "Warning: Recurring hook 'my_sync' is scheduled 2 times."));
+        assert!(details.iter().any(|d| d == "Warning: Hook 'my_sync' runs every 60 seconds (1 minute)."));
+        assert!(details.iter().any(|d| d == "Note: Events from inactive plugin 'wordfence': wordfence_daily_cron"));
+        assert!(details.iter().any(|d| d.starts_with("Critical: DISABLE_WP_CRON is set")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_runs_wp_cron() {
+        assert!(runs_wp_cron("*/5 * * * * www-data curl -s https://example.com/wp-cron.php?doing_wp_cron > /dev/null"));
+        assert!(runs_wp_cron("[Service]\nExecStart=/usr/local/bin/wp cron event run --due-now --path=/var/www/html"));
+        assert!(!runs_wp_cron("# */5 * * * * wget -q https://example.com/wp-cron.php"));
+    }
+}
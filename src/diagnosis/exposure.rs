@@ -0,0 +1,225 @@
+use crate::diagnosis::Status;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+use reqwest::header::RANGE;
+use reqwest::Url;
+use walkdir::WalkDir;
+
+/// Deep enough for `wp-content/uploads/<year>/<month>/`.
+const MAX_DEPTH: usize = 5;
+
+/// Only the start of each file is downloaded, dumps can be gigabytes.
+const SNIFF_BYTES: u64 = 4096;
+
+const DUMP_SUFFIXES: &[&str] = &[".sql", ".sql.gz", ".sql.bz2", ".sql.zip"];
+const SOURCE_BACKUP_SUFFIXES: &[&str] = &[".php.bak", ".php.old", ".php.orig", ".php.save", ".php.swp", ".php~"];
+const LOG_NAMES: &[&str] = &["debug.log", "error_log", "php_errorlog"];
+
+/// A sensitive file found under the WordPress root.
+#[derive(Debug, PartialEq)]
+pub struct Exposure {
+    /// Relative to the WordPress root, with `/` separators.
+    pub path: String,
+    pub kind: &'static str,
+    /// `None` when the URL could not be requested.
+    pub reachable: Option<bool>,
+}
+
+/// `siteurl` is `None` when it could not be read; only files on disk are checked then.
+pub fn check(root: &Path, siteurl: Option<&str>, status: &mut Status, details: &mut Vec<String>) {
+    let candidates = find_candidates(root);
+    let mut exposures: Vec<Exposure> = candidates.iter()
+        .map(|(path, kind, _)| Exposure { path: path.clone(), kind, reachable: None })
+        .collect();
+
+    // Files on disk are served relative to siteurl, which differs from home when WordPress lives in a subdirectory
+    let base = siteurl.and_then(|s| Url::parse(&format!("{}/", s.trim_end_matches('/'))).ok());
+    let client = reqwest::blocking::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(15))
+        .build();
+    let (base, client) = match (base, client) {
+        (Some(base), Ok(client)) => (base, client),
+        _ => {
+            details.push("Could not request the site, only checked files on disk.".to_string());
+            analyze(&exposures, None, status, details);
+            return;
+        }
+    };
+
+    for (exposure, (_, _, probe)) in exposures.iter_mut().zip(&candidates) {
+        exposure.reachable = base.join(probe).ok().and_then(|url| is_served(&client, &url, exposure.kind));
+    }
+    let listing = base.join("wp-content/uploads/").ok().and_then(|url| has_directory_listing(&client, &url));
+    analyze(&exposures, listing, status, details);
+}
+
+/// Sensitive files and directories under `root`, as (path, kind, path to request).
+/// For directories the path to request is a file inside them that proves the directory is served.
+pub fn find_candidates(root: &Path) -> Vec<(String, &'static str, String)> {
+    let mut found = Vec::new();
+    let walker = WalkDir::new(root).min_depth(1).max_depth(MAX_DEPTH).into_iter()
+        .filter_entry(|e| !matches!(e.file_name().to_str(), Some("node_modules")));
+    let mut skip_below: Option<std::path::PathBuf> = None;
+
+    for entry in walker.filter_map(|e| e.ok()) {
+        if skip_below.as_ref().is_some_and(|dir| entry.path().starts_with(dir)) {
+            continue;
+        }
+        let rel = match entry.path().strip_prefix(root) {
+            Ok(rel) => rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"),
+            Err(_) => continue,
+        };
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+
+        if entry.file_type().is_dir() {
+            let probe = match name.as_str() {
+                ".git" => Some(("Git repository", "HEAD")),
+                ".svn" => Some(("Subversion metadata", "wc.db")),
+                _ => None,
+            };
+            if let Some((kind, file)) = probe {
+                found.push((rel.clone(), kind, format!("{}/{}", rel, file)));
+                skip_below = Some(entry.path().to_path_buf());
+            }
+            continue;
+        }
+
+        let kind = if name == ".env" || name.starts_with(".env.") {
+            "environment file"
+        } else if LOG_NAMES.contains(&name.as_str()) {
+            "log file"
+        } else if DUMP_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+            "database dump"
+        } else if name.trim_start_matches('.').starts_with("wp-config") && name != "wp-config.php" && name != "wp-config-sample.php" {
+            "wp-config.php backup"
+        } else if SOURCE_BACKUP_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+            "PHP source backup"
+        } else {
+            continue;
+        };
+        found.push((rel.clone(), kind, rel));
+    }
+    found
+}
+
+/// Whether the server hands out the file itself, rather than a 404 or a catch-all HTML page.
+fn is_served(client: &reqwest::blocking::Client, url: &Url, kind: &str) -> Option<bool> {
+    let response = client.get(url.clone()).header(RANGE, format!("bytes=0-{}", SNIFF_BYTES - 1)).send().ok()?;
+    if !(response.status().as_u16() == 200 || response.status().as_u16() == 206) {
+        return Some(false);
+    }
+    let mut body = Vec::new();
+    response.take(SNIFF_BYTES).read_to_end(&mut body).ok()?;
+    let text = String::from_utf8_lossy(&body).to_lowercase();
+    if kind == "Git repository" {
+        return Some(text.starts_with("ref:") || text.trim().len() == 40);
+    }
+    let html = text.trim_start().starts_with("<!doctype html") || text.trim_start().starts_with("<html");
+    Some(!body.is_empty() && !html)
+}
+
+fn has_directory_listing(client: &reqwest::blocking::Client, url: &Url) -> Option<bool> {
+    let response = client.get(url.clone()).send().ok()?;
+    if !response.status().is_success() {
+        return Some(false);
+    }
+    let mut body = Vec::new();
+    response.take(64 * 1024).read_to_end(&mut body).ok()?;
+    let text = String::from_utf8_lossy(&body);
+    Some(text.contains("Index of /") || text.contains("Directory listing for"))
+}
+
+pub fn analyze(exposures: &[Exposure], listing: Option<bool>, status: &mut Status, details: &mut Vec<String>) {
+    if exposures.is_empty() {
+        details.push("No sensitive files found under the WordPress root.".to_string());
+    }
+    for exposure in exposures {
+        match exposure.reachable {
+            Some(true) => {
+                status.escalate(Status::Error);
+                details.push(format!("Critical: {} is publicly reachable: {}", exposure.kind, exposure.path));
+            }
+            Some(false) => details.push(format!("Note: {} exists on disk but is not served: {}", exposure.kind, exposure.path)),
+            None => {
+                status.escalate(Status::Warning);
+                details.push(format!("Warning: {} exists on disk, could not check whether it is served: {}", exposure.kind, exposure.path));
+            }
+        }
+    }
+
+    match listing {
+        Some(true) => {
+            status.escalate(Status::Warning);
+            details.push("Warning: Directory listing is enabled on wp-content/uploads/. Disable it with `Options -Indexes` (Apache) or `autoindex off` (nginx).".to_string());
+        }
+        Some(false) => details.push("Directory listing on wp-content/uploads/: disabled".to_string()),
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use anyhow::Result;
+
+    #[test]
+    fn test_find_candidates_and_analyze() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        for file in ["wp-config.php", "wp-config.php.bak", ".env", "wp-content/debug.log",
+            "wp-content/uploads/2024/05/backup.sql.gz", "wp-content/uploads/2024/05/photo.jpg", ".git/HEAD", ".git/config"] {
+            let path = root.path().join(file);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, "x")?;
+        }
+
+        let mut found = find_candidates(root.path());
+        found.sort();
+        assert_eq!(found, vec![
+            (".env".to_string(), "environment file", ".env".to_string()),
+            (".git".to_string(), "Git repository", ".git/HEAD".to_string()),
+            ("wp-config.php.bak".to_string(), "wp-config.php backup", "wp-config.php.bak".to_string()),
+            ("wp-content/debug.log".to_string(), "log file", "wp-content/debug.log".to_string()),
+            ("wp-content/uploads/2024/05/backup.sql.gz".to_string(), "database dump", "wp-content/uploads/2024/05/backup.sql.gz".to_string()),
+        ]);
+
+        let exposures = vec![
+            Exposure { path: ".env".to_string(), kind: "environment file", reachable: Some(true) },
+            Exposure { path: "wp-content/debug.log".to_string(), kind: "log file", reachable: Some(false) },
+        ];
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        analyze(&exposures, Some(true), &mut status, &mut details);
+
+        assert_eq!(status, Status::Error);
+        assert_eq!(details[0], "Critical: environment file is publicly reachable: .env");
+        assert_eq!(details[1], "Note: log file exists on disk but is not served: wp-content/debug.log");
+        assert!(details[2].starts_with("Warning: Directory listing is enabled"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_served_ignores_catch_all_pages() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut request_line = String::new();
+                BufReader::new(&stream).read_line(&mut request_line).unwrap();
+                let body = if request_line.starts_with("GET /.env ") { "DB_PASSWORD=secret\n" } else { "<!DOCTYPE html><html>Home</html>" };
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body).unwrap();
+            }
+        });
+
+        let client = reqwest::blocking::Client::new();
+        let base = Url::parse(&format!("http://{}/", addr))?;
+        assert_eq!(is_served(&client, &base.join(".env")?, "environment file"), Some(true));
+        assert_eq!(is_served(&client, &base.join("wp-config.php.bak")?, "wp-config.php backup"), Some(false));
+        Ok(())
+    }
+}
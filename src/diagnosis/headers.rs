@@ -0,0 +1,141 @@
+use crate::diagnosis::{SiteUrls, Status};
+use std::time::Duration;
+
+/// Security headers every page should send, with the value we recommend.
+/// The status is how serious it is when the header is missing, `Ok` makes it a note.
+const EXPECTED: &[(&str, &str, &str, Status)] = &[
+    ("x-content-type-options", "X-Content-Type-Options", "nosniff", Status::Warning),
+    ("x-frame-options", "X-Frame-Options", "SAMEORIGIN (or CSP frame-ancestors 'self')", Status::Warning),
+    ("content-security-policy", "Content-Security-Policy", "a policy with at least frame-ancestors 'self'; object-src 'none'; base-uri 'self'", Status::Ok),
+    ("referrer-policy", "Referrer-Policy", "strict-origin-when-cross-origin", Status::Ok),
+    ("permissions-policy", "Permissions-Policy", "camera=(), microphone=(), geolocation=() for features the site does not use", Status::Ok),
+];
+
+/// Referrer policies that send full URLs, including query strings, to other sites.
+const LEAKY_REFERRER_POLICIES: &[&str] = &["unsafe-url", "no-referrer-when-downgrade"];
+
+pub fn check(urls: &SiteUrls, status: &mut Status, details: &mut Vec<String>) {
+    let (home, siteurl) = (urls.home.trim_end_matches('/'), urls.siteurl.trim_end_matches('/'));
+    let client = match reqwest::blocking::Client::builder().timeout(Duration::from_secs(30)).build() {
+        Ok(client) => client,
+        Err(e) => {
+            details.push(format!("Could not create HTTP client: {}", e));
+            return;
+        }
+    };
+
+    let mut pages = Vec::new();
+    for (label, url) in [("front end", format!("{}/", home)), ("login page", format!("{}/wp-login.php", siteurl))] {
+        match client.get(&url).send() {
+            Ok(response) => {
+                let headers = response.headers().iter()
+                    .map(|(name, value)| (name.as_str().to_string(), String::from_utf8_lossy(value.as_bytes()).to_string()))
+                    .collect();
+                pages.push((label, headers));
+            }
+            Err(e) => details.push(format!("Could not fetch {} ({}) for the header audit: {}", label, url, e)),
+        }
+    }
+    analyze(&pages, status, details);
+}
+
+/// Audits the response headers of each page. Header names must be lowercase.
+/// Findings shared by several pages are reported once, listing the pages.
+pub fn analyze(pages: &[(&str, Vec<(String, String)>)], status: &mut Status, details: &mut Vec<String>) {
+    let mut findings: Vec<(Status, String, Vec<&str>)> = Vec::new();
+    for (label, headers) in pages {
+        for (severity, finding) in inspect(headers) {
+            match findings.iter_mut().find(|(_, f, _)| *f == finding) {
+                Some((_, _, labels)) => labels.push(label),
+                None => findings.push((severity, finding, vec![label])),
+            }
+        }
+    }
+
+    if findings.is_empty() && !pages.is_empty() {
+        details.push("Security headers: OK".to_string());
+    }
+    for (severity, finding, labels) in findings {
+        status.escalate(severity);
+        let prefix = if severity == Status::Warning { "Warning" } else { "Note" };
+        details.push(format!("{}: {} ({}).", prefix, finding, labels.join(", ")));
+    }
+}
+
+fn inspect(headers: &[(String, String)]) -> Vec<(Status, String)> {
+    let get = |name: &str| headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.trim());
+    let mut findings = Vec::new();
+    let frame_ancestors = get("content-security-policy").is_some_and(|csp| csp.contains("frame-ancestors"));
+
+    for (key, name, recommended, severity) in EXPECTED {
+        match get(key) {
+            None if *key == "x-frame-options" && frame_ancestors => {}
+            None => findings.push((*severity, format!("{} is not set, recommended: {}", name, recommended))),
+            Some(value) => {
+                let weak = match *key {
+                    "x-content-type-options" => !value.eq_ignore_ascii_case("nosniff"),
+                    "x-frame-options" => !["deny", "sameorigin"].contains(&value.to_lowercase().as_str()),
+                    "referrer-policy" => LEAKY_REFERRER_POLICIES.iter().any(|p| value.eq_ignore_ascii_case(p)),
+                    _ => false,
+                };
+                if weak {
+                    findings.push((*severity, format!("{} is \"{}\", recommended: {}", name, value, recommended)));
+                }
+            }
+        }
+    }
+
+    if let Some(powered_by) = get("x-powered-by") {
+        if powered_by.chars().any(|c| c.is_ascii_digit()) {
+            findings.push((Status::Warning, format!("X-Powered-By reveals \"{}\", set expose_php = Off in php.ini", powered_by)));
+        } else {
+            findings.push((Status::Ok, format!("X-Powered-By is sent (\"{}\"), it can be removed", powered_by)));
+        }
+    }
+    if let Some(server) = get("server") {
+        if server.chars().any(|c| c.is_ascii_digit()) {
+            findings.push((Status::Warning, format!(
+                "Server header reveals \"{}\", use ServerTokens Prod (Apache) or server_tokens off (nginx)", server)));
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_analyze_security_headers() {
+        let front = headers(&[
+            ("server", "Apache/2.4.41 (Ubuntu)"),
+            ("x-powered-by", "PHP/7.4.3"),
+            ("x-content-type-options", "nosniff"),
+            ("content-security-policy", "frame-ancestors 'self'"),
+            ("referrer-policy", "unsafe-url"),
+            ("permissions-policy", "camera=()"),
+        ]);
+        let login = headers(&[
+            ("server", "Apache/2.4.41 (Ubuntu)"),
+            ("x-frame-options", "ALLOWALL"),
+        ]);
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+
+        analyze(&[("front end", front), ("login page", login)], &mut status, &mut details);
+
+        assert_eq!(status, Status::Warning);
+        assert!(details.contains(&"Warning: Server header reveals \"Apache/2.4.41 (Ubuntu)\", use ServerTokens Prod (Apache) or server_tokens off (nginx) (front end, login page).".to_string()));
+        assert!(details.contains(&"Warning: X-Powered-By reveals \"PHP/7.4.3\", set expose_php = Off in php.ini (front end).".to_string()));
+        assert!(details.contains(&"Warning: X-Content-Type-Options is not set, recommended: nosniff (login page).".to_string()));
+        assert!(details.iter().any(|d| d.starts_with("Warning: X-Frame-Options is \"ALLOWALL\"") && d.ends_with("(login page).")));
+        assert!(details.iter().any(|d| d.starts_with("Note: Referrer-Policy is \"unsafe-url\"")));
+        assert!(details.iter().any(|d| d.starts_with("Note: Content-Security-Policy is not set") && d.ends_with("(login page).")));
+        // frame-ancestors replaces X-Frame-Options on the front end
+        assert!(!details.iter().any(|d| d.starts_with("Warning: X-Frame-Options is not set")));
+    }
+}
@@ -0,0 +1,247 @@
+use crate::diagnosis::Status;
+use crate::wp::WpCli;
+use std::path::Path;
+use anyhow::Result;
+use regex::Regex;
+use reqwest::Url;
+
+/// Rows fetched per table. Infected sites usually have the same payload in every row.
+const MAX_ROWS: usize = 500;
+
+/// Characters of context kept around a match.
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Script hosts common enough that a tag pointing to them is not worth reporting.
+const KNOWN_SCRIPT_HOSTS: &[&str] = &[
+    "googletagmanager.com", "google-analytics.com", "google.com", "gstatic.com", "googleapis.com",
+    "youtube.com", "facebook.net", "twitter.com", "jsdelivr.net", "cloudflare.com", "jquery.com",
+    "stripe.com", "paypal.com", "hubspot.com", "hs-scripts.com", "vimeo.com", "wp.com",
+];
+
+/// A database row worth inspecting.
+#[derive(Debug)]
+pub struct Row {
+    pub table: String,
+    pub id: String,
+    /// The option name, for rows from the options table.
+    pub option: Option<String>,
+    pub content: String,
+}
+
+struct Patterns {
+    script_src: Regex,
+    obfuscated_script: Regex,
+    hidden_links: Regex,
+    payload: Regex,
+}
+
+impl Patterns {
+    fn new() -> Patterns {
+        let compile = |pattern: &str| Regex::new(pattern).expect("valid regex");
+        Patterns {
+            script_src: compile(r#"(?i)<script[^>]*\ssrc\s*=\s*["']?(?:https?:)?//([^/"'\s>:]+)"#),
+            obfuscated_script: compile(r"(?is)<script[^>]*>.{0,2000}?(eval\(|atob\(|String\.fromCharCode|document\.write\(unescape|\\x[0-9a-f]{2}\\x[0-9a-f]{2})"),
+            hidden_links: compile(r#"(?is)<(div|span|p)[^>]*style\s*=\s*["'][^"']*(display\s*:\s*none|visibility\s*:\s*hidden|left\s*:\s*-\d{3,}px|font-size\s*:\s*0)[^>]*>.{0,2000}?<a\s[^>]*href"#),
+            payload: compile(r"(?i)(eval\s*\(|base64_decode\s*\(|gzinflate\s*\(|str_rot13\s*\(|create_function\s*\()"),
+        }
+    }
+}
+
+pub fn check(wp: &WpCli, root: &Path, status: &mut Status, details: &mut Vec<String>) {
+    let urls = wp.run(&["config", "get", "table_prefix"], root).and_then(|prefix| {
+        let prefix = prefix.trim().to_string();
+        Ok((raw_option(wp, root, &prefix, "siteurl")?, raw_option(wp, root, &prefix, "home")?, prefix))
+    });
+    let (siteurl, home, prefix) = match urls {
+        Ok(urls) => urls,
+        Err(e) => {
+            details.push(format!("Could not scan the database: {}", e));
+            return;
+        }
+    };
+    analyze_urls(&siteurl, &home, status, details);
+
+    let mut rows = Vec::new();
+    let posts = format!(
+        "SELECT ID, HEX(post_content) FROM {p}posts WHERE post_type <> 'revision' AND post_status NOT IN ('trash', 'auto-draft') \
+         AND (post_content LIKE '%<script%' OR post_content LIKE '%display:%none%' OR post_content LIKE '%visibility:%hidden%' \
+         OR post_content LIKE '%left:%-%px%' OR post_content LIKE '%font-size:%0%') LIMIT {n}",
+        p = prefix, n = MAX_ROWS);
+    let options = format!(
+        "SELECT option_id, option_name, HEX(option_value) FROM {p}options \
+         WHERE option_name NOT LIKE '\\_transient\\_%' AND option_name NOT LIKE '\\_site\\_transient\\_%' \
+         AND (option_value LIKE '%<script%' OR option_value LIKE '%eval(%' OR option_value LIKE '%base64\\_decode%' \
+         OR option_value LIKE '%gzinflate%' OR option_value LIKE '%str\\_rot13%' OR option_value LIKE '%create\\_function%' \
+         OR option_value LIKE '%display:%none%' OR option_value LIKE '%visibility:%hidden%') LIMIT {n}",
+        p = prefix, n = MAX_ROWS);
+
+    for (table, query, has_name) in [(format!("{}posts", prefix), posts, false), (format!("{}options", prefix), options, true)] {
+        match wp.run(&["db", "query", &query, "--skip-column-names"], root) {
+            Ok(output) => rows.extend(parse_rows(&table, &output, has_name)),
+            Err(e) => details.push(format!("Could not scan {}: {}", table, e)),
+        }
+    }
+
+    let own_hosts: Vec<String> = [&siteurl, &home].iter()
+        .filter_map(|u| Url::parse(u).ok().and_then(|u| u.host_str().map(|h| h.trim_start_matches("www.").to_string())))
+        .collect();
+    analyze(&rows, &own_hosts, status, details);
+}
+
+/// Reads an option straight from the database, bypassing WP_HOME/WP_SITEURL and filters.
+fn raw_option(wp: &WpCli, root: &Path, prefix: &str, name: &str) -> Result<String> {
+    let query = format!("SELECT HEX(option_value) FROM {}options WHERE option_name = '{}'", prefix, name);
+    let output = wp.run(&["db", "query", &query, "--skip-column-names"], root)?;
+    from_hex(output.trim()).ok_or_else(|| anyhow::anyhow!("unexpected output for option {}", name))
+}
+
+/// Parses `id<TAB>[name<TAB>]hex` lines. Contents are hex encoded so tabs and newlines survive.
+fn parse_rows(table: &str, output: &str, has_name: bool) -> Vec<Row> {
+    output.lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let id = fields.next()?.to_string();
+            let option = if has_name { Some(fields.next()?.to_string()) } else { None };
+            let content = from_hex(fields.next()?)?;
+            Some(Row { table: table.to_string(), id, option, content })
+        })
+        .collect()
+}
+
+fn from_hex(hex: &str) -> Option<String> {
+    let pairs = hex.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    let bytes: Option<Vec<u8>> = pairs
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect();
+    Some(String::from_utf8_lossy(&bytes?).to_string())
+}
+
+/// Flags siteurl/home values that carry markup or point to different domains.
+pub fn analyze_urls(siteurl: &str, home: &str, status: &mut Status, details: &mut Vec<String>) {
+    for (name, value) in [("siteurl", siteurl), ("home", home)] {
+        if value.contains(['<', '>', '"', '\'', ' ']) || Url::parse(value.trim()).is_err() {
+            status.escalate(Status::Error);
+            details.push(format!("Critical: The {} option is not a plain URL: `{}`", name, redact(value)));
+        }
+    }
+    let host = |u: &str| Url::parse(u.trim()).ok().and_then(|u| u.host_str().map(|h| h.trim_start_matches("www.").to_string()));
+    if let (Some(site_host), Some(home_host)) = (host(siteurl), host(home)) {
+        if site_host != home_host {
+            status.escalate(Status::Warning);
+            details.push(format!("Warning: siteurl ({}) and home ({}) point to different domains in the database. Check that this is intended.", site_host, home_host));
+        }
+    }
+}
+
+pub fn analyze(rows: &[Row], own_hosts: &[String], status: &mut Status, details: &mut Vec<String>) {
+    let patterns = Patterns::new();
+    let mut hits = 0;
+
+    for row in rows {
+        let location = match &row.option {
+            Some(name) if name.starts_with("widget_") => format!("{} row {} (widget {})", row.table, row.id, name),
+            Some(name) => format!("{} row {} (option {})", row.table, row.id, name),
+            None => format!("{} row {}", row.table, row.id),
+        };
+        let mut report = |level: Status, reason: String, start: usize, end: usize| {
+            hits += 1;
+            status.escalate(level);
+            let prefix = if level == Status::Error { "Critical" } else { "Warning" };
+            details.push(format!("{}: {} in {}: `{}`", prefix, reason, location, redact(&snippet(&row.content, start, end))));
+        };
+
+        for caps in patterns.script_src.captures_iter(&row.content) {
+            let host = caps[1].to_lowercase();
+            let known = own_hosts.iter().map(String::as_str).chain(KNOWN_SCRIPT_HOSTS.iter().copied())
+                .any(|k| host == k || host.ends_with(&format!(".{}", k)));
+            if !known {
+                let m = caps.get(0).expect("whole match");
+                report(Status::Error, format!("Script from unknown domain {}", defang(&host)), m.start(), m.end());
+            }
+        }
+        if let Some(m) = patterns.obfuscated_script.find(&row.content) {
+            report(Status::Error, "Obfuscated inline script".to_string(), m.start(), m.end());
+        }
+        if let Some(m) = patterns.hidden_links.find(&row.content) {
+            report(Status::Warning, "Hidden block with links (SEO spam?)".to_string(), m.start(), m.end());
+        }
+        // Code in post content is usually a tutorial, in options it is a payload
+        if row.option.is_some() {
+            if let Some(m) = patterns.payload.find(&row.content) {
+                report(Status::Error, "PHP payload".to_string(), m.start(), m.end());
+            }
+        }
+    }
+
+    if hits == 0 {
+        details.push(format!("Database scan: no injected scripts or spam found ({} candidate rows checked).", rows.len()));
+    }
+}
+
+/// The match with some context, on one line.
+fn snippet(content: &str, start: usize, end: usize) -> String {
+    let floor = |mut i: usize| { while !content.is_char_boundary(i) { i -= 1; } i };
+    let from = floor(start.saturating_sub(SNIPPET_CONTEXT));
+    let to = floor((end + SNIPPET_CONTEXT).min(content.len()));
+    let to = if to - from > 200 { floor(from + 200) } else { to };
+    content[from..to].split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Makes a snippet safe to paste: URLs are defanged and long encoded blobs shortened.
+fn redact(text: &str) -> String {
+    let blob = Regex::new(r"[A-Za-z0-9+/=]{24,}").expect("valid regex");
+    let shortened = blob.replace_all(text, |caps: &regex::Captures| format!("{}…[{} chars]", &caps[0][..8], caps[0].len()));
+    shortened.replace("http", "hxxp")
+}
+
+fn defang(host: &str) -> String {
+    host.replace('.', "[.]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_injected_rows() {
+        let rows = vec![
+            Row { table: "wp_posts".to_string(), id: "12".to_string(), option: None,
+                content: "<p>Hello</p><script src='https://cdn.evil-stats.xyz/t.js'></script><script src=\"https://www.googletagmanager.com/gtm.js\"></script>".to_string() },
+            Row { table: "wp_posts".to_string(), id: "40".to_string(), option: None,
+                content: "<div style=\"display:none\">Buy <a href=\"http://pills.example\">cheap pills</a></div>".to_string() },
+            Row { table: "wp_options".to_string(), id: "7".to_string(), option: Some("widget_custom_html".to_string()),
+                content: "a:1:{s:7:\"content\";s:60:\"<?php eval(base64_decode('ZXZhbCgkX1BPU1RbJ3gnXSk7ZWNobyAxOw==')); ?>\";}".to_string() },
+            Row { table: "wp_posts".to_string(), id: "41".to_string(), option: None,
+                content: "<script src=\"https://example.com/wp-includes/js/jquery.js\"></script>".to_string() },
+        ];
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+
+        analyze(&rows, &["example.com".to_string()], &mut status, &mut details);
+
+        assert_eq!(status, Status::Error);
+        assert_eq!(details.len(), 3);
+        assert!(details[0].starts_with("Critical: Script from unknown domain cdn[.]evil-stats[.]xyz in wp_posts row 12: `"));
+        assert!(details[0].contains("hxxps://cdn.evil-stats.xyz/t.js"));
+        assert!(details[1].starts_with("Warning: Hidden block with links (SEO spam?) in wp_posts row 40"));
+        assert!(details[2].starts_with("Critical: PHP payload in wp_options row 7 (widget widget_custom_html): `"));
+        assert!(details[2].contains("base64_decode('ZXZhbCgk…["));
+    }
+
+    #[test]
+    fn test_parse_rows_and_urls() {
+        let rows = parse_rows("wp_options", "5\twidget_text\t3C623E\n", true);
+        assert_eq!(rows[0].option.as_deref(), Some("widget_text"));
+        assert_eq!(rows[0].content, "<b>");
+
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        analyze_urls("https://example.com/\"><script src=//x.io>", "https://spam.example.net", &mut status, &mut details);
+        assert_eq!(status, Status::Error);
+        assert!(details[0].starts_with("Critical: The siteurl option is not a plain URL"));
+        assert!(details[1].starts_with("Warning: siteurl (example.com) and home (spam.example.net) point to different domains"));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::diagnosis::Status;
+use crate::wp::WpCli;
+use std::collections::BTreeMap;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use anyhow::Result;
+use reqwest::Url;
+use serde::Deserialize;
+
+/// Requests the endpoints from inside WordPress, through the same HTTP API
+/// (and DNS, proxy and firewall path) that cron and the editors use.
+const LOOPBACK_PHP: &str = r#"
+$checks = array(
+    'rest' => rest_url(),
+    'cron' => site_url( 'wp-cron.php' ),
+    'ajax' => admin_url( 'admin-ajax.php' ),
+);
+$results = array();
+foreach ( $checks as $name => $url ) {
+    $response = wp_remote_get( $url, array(
+        'timeout'   => 10,
+        'sslverify' => apply_filters( 'https_local_ssl_verify', false ),
+    ) );
+    if ( is_wp_error( $response ) ) {
+        $results[ $name ] = array( 'url' => $url, 'error' => $response->get_error_message() );
+    } else {
+        $results[ $name ] = array(
+            'url'  => $url,
+            'code' => wp_remote_retrieve_response_code( $response ),
+            'auth' => wp_remote_retrieve_header( $response, 'www-authenticate' ),
+            'body' => substr( wp_remote_retrieve_body( $response ), 0, 200 ),
+        );
+    }
+}
+echo json_encode( $results );
+"#;
+
+#[derive(Deserialize, Debug)]
+pub struct LoopbackResult {
+    pub url: String,
+    #[serde(default)]
+    pub code: Option<u16>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub auth: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+pub fn check(wp: &WpCli, root: &Path, home: Option<&str>, status: &mut Status, details: &mut Vec<String>) {
+    // Show where the home host resolves to, the usual suspect when the server cannot reach itself
+    if let Some(home) = home {
+        if let Ok(url) = Url::parse(home) {
+            let host = url.host_str().unwrap_or_default().to_string();
+            match (host.as_str(), url.port_or_known_default().unwrap_or(80)).to_socket_addrs() {
+                Ok(addrs) => {
+                    let addrs: Vec<String> = addrs.map(|a| a.ip().to_string()).collect();
+                    details.push(format!("{} resolves to {} on this server", host, addrs.join(", ")));
+                }
+                Err(e) => {
+                    status.escalate(Status::Error);
+                    details.push(format!("Critical: {} does not resolve on this server: {}", host, e));
+                }
+            }
+        }
+    }
+
+    match wp.run(&["eval", LOOPBACK_PHP], root).and_then(|out| parse(&out)) {
+        Ok(results) => analyze(&results, status, details),
+        Err(e) => details.push(format!("Could not run loopback checks: {}", e)),
+    }
+}
+
+fn parse(output: &str) -> Result<BTreeMap<String, LoopbackResult>> {
+    // Plugins may print notices before our JSON
+    let start = output.find('{').ok_or_else(|| anyhow::anyhow!("no JSON in output"))?;
+    Ok(serde_json::from_str(&output[start..])?)
+}
+
+pub fn analyze(results: &BTreeMap<String, LoopbackResult>, status: &mut Status, details: &mut Vec<String>) {
+    for (name, label, breaks) in [
+        ("rest", "REST API", "the block editor and many plugins"),
+        ("cron", "Loopback to wp-cron.php", "scheduled events"),
+        ("ajax", "Loopback to admin-ajax.php", "AJAX features and plugin/theme file editing"),
+    ] {
+        let result = match results.get(name) {
+            Some(result) => result,
+            None => continue,
+        };
+        match problem(name, result) {
+            None => details.push(format!("{}: OK ({})", label, result.code.unwrap_or_default())),
+            Some(reason) => {
+                status.escalate(Status::Error);
+                details.push(format!("Critical: {} failed: {} ({}). This breaks {}.", label, reason, result.url, breaks));
+            }
+        }
+    }
+}
+
+/// Explains why a loopback result is a failure, or `None` if it is healthy.
+fn problem(name: &str, result: &LoopbackResult) -> Option<String> {
+    if let Some(error) = &result.error {
+        let lower = error.to_lowercase();
+        let cause = if lower.contains("resolve host") || lower.contains("curl error 6") {
+            "the server cannot resolve its own hostname (DNS)"
+        } else if lower.contains("timed out") || lower.contains("curl error 28") {
+            "the request timed out (firewall or blocked outgoing connection?)"
+        } else if lower.contains("connect") || lower.contains("curl error 7") {
+            "the server cannot connect to itself (firewall or wrong IP in DNS/hosts?)"
+        } else if lower.contains("ssl") || lower.contains("certificate") {
+            "TLS error"
+        } else {
+            "request error"
+        };
+        return Some(format!("{}: {}", cause, error));
+    }
+
+    let code = result.code.unwrap_or_default();
+    if code == 401 || result.auth.as_deref().is_some_and(|a| !a.is_empty()) {
+        return Some(format!("HTTP {} (the site is behind HTTP basic authentication)", code));
+    }
+    let healthy = match name {
+        // admin-ajax.php answers 400 "0" when called without an action
+        "ajax" => code == 200 || code == 400,
+        "rest" => code == 200 && result.body.as_deref().is_some_and(|b| b.trim_start().starts_with('{')),
+        _ => code == 200,
+    };
+    match (healthy, code) {
+        (true, _) => None,
+        (false, 403) => Some("HTTP 403 (blocked by a security plugin, WAF or server rule)".to_string()),
+        (false, 200) => Some("HTTP 200 but the response is not JSON (REST API disabled or rewritten?)".to_string()),
+        (false, code) => Some(format!("HTTP {}", code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_loopback_results() -> Result<()> {
+        let output = r#"Notice: something deprecated
+{"rest": {"url": "https://example.com/wp-json/", "code": 401, "auth": "Basic realm=\"Staging\"", "body": ""},
+ "cron": {"url": "https://example.com/wp-cron.php", "error": "cURL error 6: Could not resolve host: example.com"},
+ "ajax": {"url": "https://example.com/wp-admin/admin-ajax.php", "code": 400, "auth": "", "body": "0"}}"#;
+        let results = parse(output)?;
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+
+        analyze(&results, &mut status, &mut details);
+
+        assert_eq!(status, Status::Error);
+        assert!(details.iter().any(|d| d.starts_with("Critical: REST API failed: HTTP 401 (the site is behind HTTP basic authentication)")));
+        assert!(details.iter().any(|d| d.starts_with("Critical: Loopback to wp-cron.php failed: the server cannot resolve its own hostname (DNS)")));
+        assert!(details.iter().any(|d| d == "Loopback to admin-ajax.php: OK (400)"));
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 use crate::diagnosis::{Diagnosis, DiagnosisReport, Status};
+use crate::fix::Fix;
+use crate::fix::actions::DeleteExpiredTransients;
 use crate::wp::WpCli;
 use std::path::Path;
 use anyhow::Result;
@@ -30,12 +32,9 @@ impl Diagnosis for MaintenanceDiagnosis {
         // Let's just check if there are many.
         // `wp option list --search="*_transient_timeout_*" --format=count` gives total transients with timeouts (approx).
         
-        match wp.run(&["option", "list", "--search=*_transient_timeout_*", "--format=count"], root) {
-             Ok(count_str) => {
-                 let count: usize = count_str.trim().parse().unwrap_or(0);
-                 details.push(format!("Found {} transient timeout records.", count));
-             }
-             Err(_) => {}
+        if let Ok(count_str) = wp.run(&["option", "list", "--search=*_transient_timeout_*", "--format=count"], root) {
+             let count: usize = count_str.trim().parse().unwrap_or(0);
+             details.push(format!("Found {} transient timeout records.", count));
         }
 
         // 3. Check Debug Log Size
@@ -60,6 +59,18 @@ impl Diagnosis for MaintenanceDiagnosis {
             details,
         })
     }
+
+    fn fixes(&self, wp: &WpCli, root: &Path) -> Result<Vec<Box<dyn Fix>>> {
+        let mut fixes: Vec<Box<dyn Fix>> = Vec::new();
+
+        if let Ok(count_str) = wp.run(&["option", "list", "--search=*_transient_timeout_*", "--format=count"], root) {
+            if count_str.trim().parse::<usize>().unwrap_or(0) > 0 {
+                fixes.push(Box::new(DeleteExpiredTransients));
+            }
+        }
+
+        Ok(fixes)
+    }
 }
 
 impl MaintenanceDiagnosis {
@@ -69,7 +80,7 @@ impl MaintenanceDiagnosis {
         
         if count > 1000 {
             *status = Status::Warning;
-            details.push("Warning: High number of post revisions (> 1000). Consider cleaning them.".to_string());
+            details.push("Warning: High number of post revisions (> 1000). Run `wp-agent cleanup` to remove the excess.".to_string());
         }
     }
 
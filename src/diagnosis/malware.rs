@@ -0,0 +1,239 @@
+use crate::diagnosis::Status;
+use crate::store;
+use std::cmp::Reverse;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use anyhow::Result;
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+/// Files bigger than this are not read, webshells are small.
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// PHP files modified within this many days are listed.
+const RECENT_DAYS: u64 = 7;
+
+const MAX_LISTED: usize = 10;
+
+const PHP_EXTENSIONS: &[&str] = &["php", "phtml", "php3", "php4", "php5", "php7", "pht", "phar"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Critical,
+}
+
+/// A signature, as stored in `~/.wp-agent/malware-rules.json`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RuleDef {
+    pub name: String,
+    /// Regular expression matched against the raw file contents.
+    pub pattern: String,
+    pub severity: Severity,
+}
+
+pub struct Rule {
+    pub name: String,
+    pub regex: Regex,
+    pub severity: Severity,
+}
+
+fn rule(name: &str, pattern: &str, severity: Severity) -> RuleDef {
+    RuleDef { name: name.to_string(), pattern: pattern.to_string(), severity }
+}
+
+pub fn default_rules() -> Vec<RuleDef> {
+    vec![
+        rule("eval of base64 data", r"eval\s*\(\s*base64_decode\s*\(", Severity::Critical),
+        rule("eval of compressed data", r"eval\s*\(\s*(gzinflate|gzuncompress|gzdecode|str_rot13)\s*\(", Severity::Critical),
+        rule("compressed base64 payload", r"gzinflate\s*\(\s*base64_decode\s*\(", Severity::Critical),
+        rule("assert on request input", r"assert\s*\(\s*\$_(GET|POST|REQUEST|COOKIE)", Severity::Critical),
+        rule("request input executed", r"\b(eval|system|shell_exec|passthru|exec|popen|proc_open)\s*\(\s*\$_(GET|POST|REQUEST|COOKIE|SERVER)", Severity::Critical),
+        rule("known webshell marker", r"(FilesMan|c99shell|r57shell|b374k|WSO \d)", Severity::Critical),
+        rule("preg_replace /e modifier", r#"preg_replace\s*\(\s*['"][^'"]*/[a-zA-Z]*e[a-zA-Z]*['"]\s*,"#, Severity::Warning),
+        rule("long encoded string", r#"['"][A-Za-z0-9+/]{2000,}={0,2}['"]"#, Severity::Warning),
+    ]
+}
+
+/// The built-in signatures plus the ones from `~/.wp-agent/malware-rules.json`.
+pub fn load_rules() -> Result<Vec<Rule>> {
+    let mut defs = default_rules();
+    let path = store::data_dir()?.join("malware-rules.json");
+    if path.is_file() {
+        let extra: Vec<RuleDef> = serde_json::from_slice(&fs::read(&path)?)
+            .map_err(|e| anyhow::anyhow!("Invalid malware rules {:?}: {}", path, e))?;
+        defs.extend(extra);
+    }
+    compile(&defs)
+}
+
+pub fn compile(defs: &[RuleDef]) -> Result<Vec<Rule>> {
+    defs.iter()
+        .map(|def| Ok(Rule {
+            name: def.name.clone(),
+            regex: Regex::new(&def.pattern).map_err(|e| anyhow::anyhow!("Invalid pattern for rule '{}': {}", def.name, e))?,
+            severity: def.severity,
+        }))
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    /// Relative to wp-content.
+    pub path: String,
+    pub reason: String,
+    pub severity: Severity,
+}
+
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub findings: Vec<Finding>,
+    /// PHP files modified within `RECENT_DAYS`, newest first.
+    pub recent: Vec<String>,
+}
+
+pub fn scan(content_dir: &Path, rules: &[Rule], now: SystemTime) -> Scan {
+    let mut result = Scan::default();
+    let mut recent = Vec::new();
+    let handler = Regex::new(r"(?i)(AddHandler|AddType|SetHandler)[^\n]*(php|x-httpd)|auto_prepend_file|auto_append_file")
+        .expect("valid regex");
+
+    for entry in WalkDir::new(content_dir).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel = match entry.path().strip_prefix(content_dir) {
+            Ok(rel) => rel.to_string_lossy().to_string(),
+            Err(_) => continue,
+        };
+        let in_uploads = rel.starts_with("uploads/");
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        let is_php = Path::new(&name).extension().and_then(|e| e.to_str()).is_some_and(|e| PHP_EXTENSIONS.contains(&e));
+        let is_htaccess = name == ".htaccess";
+        // Outside uploads only PHP is worth reading; inside it, anything may carry PHP (e.g. a fake .ico)
+        if !is_php && !is_htaccess && !in_uploads {
+            continue;
+        }
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if is_php {
+            if let Ok(modified) = metadata.modified() {
+                if now.duration_since(modified).is_ok_and(|age| age < Duration::from_secs(RECENT_DAYS * 86400)) {
+                    recent.push((modified, rel.clone()));
+                }
+            }
+        }
+        if metadata.len() > MAX_FILE_BYTES {
+            continue;
+        }
+        let contents = match fs::read(entry.path()) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+        let mut push = |reason: String, severity| result.findings.push(Finding { path: rel.clone(), reason, severity });
+
+        if is_htaccess {
+            if handler.is_match(&contents) {
+                push("enables PHP execution".to_string(), if in_uploads { Severity::Critical } else { Severity::Warning });
+            }
+            continue;
+        }
+        if in_uploads && is_php && !is_silence_placeholder(&contents) {
+            push("PHP file in uploads".to_string(), Severity::Critical);
+        } else if in_uploads && !is_php && contents.windows(5).any(|w| w.eq_ignore_ascii_case(b"<?php")) {
+            push("PHP code in a non-PHP file".to_string(), Severity::Critical);
+        }
+        for rule in rules {
+            if rule.regex.is_match(&contents) {
+                push(rule.name.clone(), rule.severity);
+            }
+        }
+    }
+
+    recent.sort_by_key(|(modified, _)| Reverse(*modified));
+    result.recent = recent.into_iter().map(|(_, path)| path).collect();
+    result
+}
+
+/// The `<?php // Silence is golden.` index files plugins drop into their upload folders.
+fn is_silence_placeholder(contents: &[u8]) -> bool {
+    contents.len() < 128 && String::from_utf8_lossy(contents).to_lowercase().contains("silence is golden")
+}
+
+pub fn analyze(scan: &Scan, status: &mut Status, details: &mut Vec<String>) {
+    if scan.findings.is_empty() {
+        details.push("Malware scan: no suspicious files in wp-content.".to_string());
+    }
+    for finding in &scan.findings {
+        match finding.severity {
+            Severity::Critical => {
+                status.escalate(Status::Error);
+                details.push(format!("Critical: {}: {}", finding.reason, finding.path));
+            }
+            Severity::Warning => {
+                status.escalate(Status::Warning);
+                details.push(format!("Warning: {}: {}", finding.reason, finding.path));
+            }
+        }
+    }
+
+    if !scan.recent.is_empty() {
+        details.push(format!("Note: {} PHP files in wp-content were modified in the last {} days:", scan.recent.len(), RECENT_DAYS));
+        for path in scan.recent.iter().take(MAX_LISTED) {
+            details.push(format!("   {}", path));
+        }
+        if scan.recent.len() > MAX_LISTED {
+            details.push(format!("   ... and {} more", scan.recent.len() - MAX_LISTED));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_flags_webshells() -> Result<()> {
+        let content = tempfile::tempdir()?;
+        let files = [
+            ("uploads/2024/05/index.php", "<?php // Silence is golden."),
+            ("uploads/2024/05/cache.php", "<?php echo 'hi';"),
+            ("uploads/2024/05/favicon_a1b2.ico", "GIF89a<?php @include $_GET['f']; ?>"),
+            ("uploads/.htaccess", "AddHandler application/x-httpd-php .jpg"),
+            ("plugins/hello/hello.php", "<?php eval ( base64_decode('ZWNobyAxOw==') );"),
+            ("plugins/hello/readme.txt", "eval(base64_decode("),
+        ];
+        for (file, body) in files {
+            let path = content.path().join(file);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, body)?;
+        }
+
+        let mut rules = default_rules();
+        rules.push(rule("custom include", r"@include\s+\$_GET", Severity::Warning));
+        let result = scan(content.path(), &compile(&rules)?, SystemTime::now());
+
+        let mut found: Vec<(&str, &str)> = result.findings.iter().map(|f| (f.path.as_str(), f.reason.as_str())).collect();
+        found.sort();
+        assert_eq!(found, vec![
+            ("plugins/hello/hello.php", "eval of base64 data"),
+            ("uploads/.htaccess", "enables PHP execution"),
+            ("uploads/2024/05/cache.php", "PHP file in uploads"),
+            ("uploads/2024/05/favicon_a1b2.ico", "PHP code in a non-PHP file"),
+            ("uploads/2024/05/favicon_a1b2.ico", "custom include"),
+        ]);
+        assert_eq!(result.recent.len(), 3);
+
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        analyze(&result, &mut status, &mut details);
+        assert_eq!(status, Status::Error);
+        assert!(details.contains(&"Note: 3 PHP files in wp-content were modified in the last 7 days:".to_string()));
+        Ok(())
+    }
+}
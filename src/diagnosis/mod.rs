@@ -1,4 +1,5 @@
 use std::path::Path;
+use crate::fix::Fix;
 use crate::wp::WpCli;
 use anyhow::Result;
 
@@ -10,15 +11,27 @@ pub struct DiagnosisReport {
     pub details: Vec<String>,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum Status {
     Ok,
     Warning,
     Error,
 }
 
+impl Status {
+    /// Raises the status to `other` if it is more severe. Never lowers it.
+    pub fn escalate(&mut self, other: Status) {
+        *self = (*self).max(other);
+    }
+}
+
 pub trait Diagnosis {
     fn run(&self, wp: &WpCli, root: &Path) -> Result<DiagnosisReport>;
+
+    /// Remediations this check can offer for the current state of the site.
+    fn fixes(&self, _wp: &WpCli, _root: &Path) -> Result<Vec<Box<dyn Fix>>> {
+        Ok(Vec::new())
+    }
 }
 
 pub mod database;
@@ -28,3 +41,68 @@ pub mod network;
 pub mod security;
 pub mod performance;
 pub mod maintenance;
+pub mod checksums;
+pub mod vulnerabilities;
+pub mod themes;
+pub mod object_cache;
+pub mod page_cache;
+pub mod cron;
+pub mod probes;
+pub mod tls;
+pub mod redirects;
+pub mod loopback;
+pub mod headers;
+pub mod exposure;
+pub mod permissions;
+pub mod malware;
+pub mod injection;
+
+/// Reads a header field like `Version:` from a theme's style.css or a plugin file,
+/// the same way WordPress' `get_file_data()` does (first 8 KB only).
+pub fn file_header(content: &str, key: &str) -> Option<String> {
+    let head = match content.char_indices().nth(8192) {
+        Some((end, _)) => &content[..end],
+        None => content,
+    };
+
+    head.lines().find_map(|line| {
+        let line = line.trim_start().trim_start_matches("<?php");
+        let line = line.trim_start_matches([' ', '\t', '/', '*', '#', '@']);
+        let (name, value) = line.split_once(':')?;
+        if !name.trim_end().eq_ignore_ascii_case(key) {
+            return None;
+        }
+        let value = value.trim().trim_end_matches("*/").trim();
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// The `home` and `siteurl` options, read once by a module and shared by the checks that request the site.
+pub struct SiteUrls {
+    pub home: String,
+    pub siteurl: String,
+}
+
+impl SiteUrls {
+    pub fn fetch(wp: &WpCli, root: &Path) -> Result<SiteUrls> {
+        Ok(SiteUrls {
+            home: wp.run(&["option", "get", "home"], root)?.trim().to_string(),
+            siteurl: wp.run(&["option", "get", "siteurl"], root)?.trim().to_string(),
+        })
+    }
+}
+
+/// Every diagnosis module, in the order they are run.
+pub fn all() -> Vec<Box<dyn Diagnosis>> {
+    vec![
+        Box::new(database::DatabaseDiagnosis),
+        Box::new(plugins::PluginDiagnosis),
+        Box::new(themes::ThemeDiagnosis),
+        Box::new(vulnerabilities::VulnerabilityDiagnosis),
+        Box::new(system::SystemDiagnosis),
+        Box::new(network::NetworkDiagnosis),
+        Box::new(security::SecurityDiagnosis),
+        Box::new(performance::PerformanceDiagnosis),
+        Box::new(maintenance::MaintenanceDiagnosis),
+    ]
+}
@@ -1,7 +1,13 @@
-use crate::diagnosis::{Diagnosis, DiagnosisReport, Status};
+use crate::diagnosis::{Diagnosis, DiagnosisReport, SiteUrls, Status};
+use crate::diagnosis::{headers, loopback, probes, redirects, tls};
 use crate::wp::WpCli;
+use std::net::ToSocketAddrs;
 use std::path::Path;
+use std::time::Duration;
 use anyhow::Result;
+use chrono::Utc;
+use reqwest::header::{LOCATION, STRICT_TRANSPORT_SECURITY};
+use reqwest::Url;
 
 pub struct NetworkDiagnosis;
 
@@ -11,37 +17,44 @@ impl Diagnosis for NetworkDiagnosis {
         let mut details = Vec::new();
         let mut overall_status = Status::Ok;
 
-        // 1. Check external connectivity (google.com)
+        // 1. Check the external services the site depends on
         println!("    > Checking external connectivity...");
-        // Using reqwest
-        match reqwest::blocking::get("https://www.google.com") {
-            Ok(_) => details.push("External connectivity (Google): OK".to_string()),
+        match probes::load() {
+            Ok(list) => {
+                for probe in &list {
+                    let result = probes::run(probe);
+                    match &result.outcome {
+                        Ok(summary) => details.push(format!("{}: OK, {} ({})", probe.name, summary, result.timings())),
+                        Err(e) => {
+                            overall_status.escalate(Status::Warning);
+                            details.push(format!("Warning: {} ({}) failed: {} ({})", probe.name, probe.url, e, result.timings()));
+                        }
+                    }
+                }
+            }
             Err(e) => {
-                overall_status = Status::Warning;
-                details.push(format!("External connectivity failed: {}", e));
+                overall_status.escalate(Status::Warning);
+                details.push(format!("Warning: {}", e));
             }
         }
 
-        // 2. Check WordPress Site Reachability
-        // Get site URL
+        // 2. Check WordPress Site Reachability, one redirect at a time so loops and mismatches show up
         println!("    > Fetching site URL...");
-        match wp.run(&["option", "get", "home"], root) {
-            Ok(url) => {
-                let url = url.trim();
-                details.push(format!("Site URL: {}", url));
+        let urls = SiteUrls::fetch(wp, root);
+        match &urls {
+            Ok(SiteUrls { home, siteurl }) => {
+                details.push(format!("Site URL: {}", home));
                 println!("    > Checking site reachability...");
-                match reqwest::blocking::get(url) {
-                    Ok(resp) => {
-                        let status = resp.status();
-                        if status.is_success() {
-                            details.push(format!("Site reachable: OK ({})", status));
-                        } else {
-                            overall_status = Status::Warning;
-                            details.push(format!("Site returned status: {}", status));
-                        }
-                    },
+                let chain = reqwest::blocking::Client::builder()
+                    .redirect(reqwest::redirect::Policy::none())
+                    .timeout(Duration::from_secs(30))
+                    .build()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|client| redirects::trace(&client, home));
+                match chain {
+                    Ok(chain) => redirects::analyze(home, siteurl, &chain, &mut overall_status, &mut details),
                     Err(e) => {
-                        overall_status = Status::Error; // Critical if site is down
+                        overall_status.escalate(Status::Error); // Critical if site is down
                         details.push(format!("Site reachable failed: {}", e));
                     }
                 }
@@ -51,6 +64,22 @@ impl Diagnosis for NetworkDiagnosis {
             }
         }
 
+        // 3. HTTPS configuration and certificate
+        println!("    > Inspecting HTTPS configuration...");
+        if let Ok(urls) = &urls {
+            self.check_https(urls, &mut overall_status, &mut details);
+        }
+
+        // 4. Requests from the server to itself, which cron and the editors rely on
+        println!("    > Checking loopback requests and REST API...");
+        loopback::check(wp, root, urls.as_ref().ok().map(|u| u.home.as_str()), &mut overall_status, &mut details);
+
+        // 5. Security response headers on the front end and login page
+        println!("    > Auditing security headers...");
+        if let Ok(urls) = &urls {
+            headers::check(urls, &mut overall_status, &mut details);
+        }
+
         Ok(DiagnosisReport {
             module: "Network".to_string(),
             status: overall_status,
@@ -59,3 +88,53 @@ impl Diagnosis for NetworkDiagnosis {
         })
     }
 }
+
+impl NetworkDiagnosis {
+    fn check_https(&self, urls: &SiteUrls, status: &mut Status, details: &mut Vec<String>) {
+        let SiteUrls { home, siteurl } = urls;
+        let url = match Url::parse(home) {
+            Ok(url) => url,
+            Err(e) => {
+                details.push(format!("Could not parse home URL {}: {}", home, e));
+                return;
+            }
+        };
+        let host = url.host_str().unwrap_or_default().to_string();
+        let client = match reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(Duration::from_secs(30))
+            .build() {
+            Ok(client) => client,
+            Err(e) => {
+                details.push(format!("Could not create HTTP client: {}", e));
+                return;
+            }
+        };
+
+        let mut http_url = url.clone();
+        let _ = http_url.set_scheme("http");
+        let _ = http_url.set_port(None);
+        let redirect = client.get(http_url).send().ok()
+            .filter(|r| r.status().is_redirection())
+            .and_then(|r| r.headers().get(LOCATION).and_then(|l| l.to_str().ok()).map(String::from));
+        let hsts = client.get(home.as_str()).send().ok()
+            .and_then(|r| r.headers().get(STRICT_TRANSPORT_SECURITY).and_then(|h| h.to_str().ok()).map(String::from));
+        tls::analyze_https(home, siteurl, redirect.as_deref(), hsts.as_deref(), status, details);
+
+        if url.scheme() != "https" {
+            return;
+        }
+        let port = url.port_or_known_default().unwrap_or(443);
+        let report = (host.as_str(), port).to_socket_addrs()
+            .map_err(anyhow::Error::from)
+            .and_then(|mut addrs| addrs.next().ok_or_else(|| anyhow::anyhow!("no address for {}", host)))
+            .and_then(|addr| tls::inspect(addr, &host, tls::mozilla_roots(), Duration::from_secs(10)));
+        match report {
+            Ok(report) => tls::analyze(&report, Utc::now(), status, details),
+            Err(e) => {
+                status.escalate(Status::Error);
+                details.push(format!("Critical: TLS handshake with {} failed: {}", host, e));
+            }
+        }
+    }
+}
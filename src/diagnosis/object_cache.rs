@@ -0,0 +1,265 @@
+use crate::diagnosis::{self, Status};
+use crate::wp::WpCli;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::Result;
+use serde_json::Value;
+
+/// Redis hit ratios are only judged once there is enough traffic to be meaningful.
+const MIN_LOOKUPS: u64 = 1000;
+const MIN_HIT_RATIO: f64 = 0.8;
+
+/// Where the Redis server of the object cache listens.
+#[derive(Debug, PartialEq)]
+pub enum RedisTarget {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+/// Checks the object-cache.php drop-in: which backend it uses, whether values
+/// persist between requests, and for Redis, how the server is doing.
+pub fn check(wp: &WpCli, root: &Path, status: &mut Status, details: &mut Vec<String>) {
+    let dropin = root.join("wp-content/object-cache.php");
+    let content = match fs::read(&dropin) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+        Err(_) => {
+            details.push("No persistent object cache (object-cache.php drop-in) installed.".to_string());
+            return;
+        }
+    };
+
+    let provider = detect_provider(&content);
+    let name = diagnosis::file_header(&content, "Plugin Name").unwrap_or_else(|| "unnamed drop-in".to_string());
+    details.push(format!("Object cache drop-in: {} ({})", name, provider));
+
+    if let Ok(cache_type) = wp.run(&["cache", "type"], root) {
+        details.push(format!("Cache type reported by WordPress: {}", cache_type.trim()));
+    }
+
+    // Every WP-CLI call is a new request, so a value only survives in a working persistent cache
+    let token = format!("wp-agent-{}", std::process::id());
+    let persisted = wp.run(&["cache", "set", "wp_agent_probe", &token, "wp-agent", "60"], root).is_ok()
+        && wp.run(&["cache", "get", "wp_agent_probe", "wp-agent"], root).is_ok_and(|v| v.trim() == token);
+    let _ = wp.run(&["cache", "delete", "wp_agent_probe", "wp-agent"], root);
+    if persisted {
+        details.push("Object cache round trip succeeded (values persist between requests).".to_string());
+    } else {
+        status.escalate(Status::Warning);
+        details.push(format!(
+            "Warning: The object cache drop-in is installed but values do not persist between requests (is the {} server reachable?).",
+            provider));
+    }
+
+    if provider.starts_with("Redis") {
+        let (target, password) = redis_settings(wp, root);
+        match redis_info(&target, &password, Duration::from_secs(5)) {
+            Ok(info) => analyze_redis_info(&info, status, details),
+            Err(e) => {
+                status.escalate(Status::Warning);
+                details.push(format!("Warning: Could not query Redis at {:?}: {}", target, e));
+            }
+        }
+    }
+}
+
+/// Guesses the cache backend from the drop-in source.
+pub fn detect_provider(content: &str) -> String {
+    let lower = content.to_lowercase();
+    let provider = if lower.contains("object cache pro") {
+        "Redis (Object Cache Pro)"
+    } else if lower.contains("litespeed") {
+        "LiteSpeed"
+    } else if lower.contains("w3 total cache") || lower.contains("w3tc") {
+        "W3 Total Cache"
+    } else if lower.contains("redis") {
+        "Redis"
+    } else if lower.contains("memcached") {
+        "Memcached"
+    } else if lower.contains("memcache") {
+        "Memcache"
+    } else if lower.contains("apcu_") {
+        "APCu"
+    } else {
+        "unknown backend"
+    };
+    provider.to_string()
+}
+
+/// Reads the connection constants used by the Redis Object Cache plugin (and most others).
+fn redis_settings(wp: &WpCli, root: &Path) -> (RedisTarget, Vec<String>) {
+    let constant = |name: &str| -> Option<Value> {
+        wp.run(&["config", "get", name, "--type=constant", "--format=json"], root)
+            .ok()
+            .and_then(|v| serde_json::from_str(v.trim()).ok())
+    };
+    let text = |value: Value| match value {
+        Value::String(s) => s,
+        other => other.to_string(),
+    };
+
+    let scheme = constant("WP_REDIS_SCHEME").map(text).unwrap_or_default();
+    let target = match constant("WP_REDIS_PATH").map(text) {
+        Some(path) if scheme == "unix" => RedisTarget::Unix(PathBuf::from(path)),
+        _ => {
+            let host = constant("WP_REDIS_HOST").map(text).unwrap_or_else(|| "127.0.0.1".to_string());
+            let port = constant("WP_REDIS_PORT").map(text).unwrap_or_else(|| "6379".to_string());
+            RedisTarget::Tcp(format!("{}:{}", host, port))
+        }
+    };
+    // A password may also be given as [username, password] for Redis ACLs
+    let password = match constant("WP_REDIS_PASSWORD") {
+        Some(Value::Array(parts)) => parts.into_iter().map(text).collect(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(value) => vec![text(value)],
+    };
+    (target, password)
+}
+
+/// Sends `INFO` to a Redis server and returns its fields.
+pub fn redis_info(target: &RedisTarget, auth: &[String], timeout: Duration) -> Result<HashMap<String, String>> {
+    let mut stream: Box<dyn ReadWrite> = match target {
+        RedisTarget::Tcp(addr) => {
+            let addr = addr.to_socket_addrs()?.next().ok_or_else(|| anyhow::anyhow!("cannot resolve {}", addr))?;
+            let stream = TcpStream::connect_timeout(&addr, timeout)?;
+            stream.set_read_timeout(Some(timeout))?;
+            Box::new(stream)
+        }
+        RedisTarget::Unix(path) => {
+            let stream = UnixStream::connect(path)?;
+            stream.set_read_timeout(Some(timeout))?;
+            Box::new(stream)
+        }
+    };
+
+    if !auth.is_empty() {
+        let mut args = vec!["AUTH"];
+        args.extend(auth.iter().map(|a| a.as_str()));
+        stream.write_all(&encode_command(&args))?;
+    }
+    stream.write_all(&encode_command(&["INFO"]))?;
+
+    let mut reader = BufReader::new(stream);
+    if !auth.is_empty() {
+        read_reply(&mut reader)?;
+    }
+    Ok(parse_info(&read_reply(&mut reader)?))
+}
+
+trait ReadWrite: Read + Write {}
+impl<T: Read + Write> ReadWrite for T {}
+
+fn encode_command(args: &[&str]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len());
+    for arg in args {
+        out.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    out.into_bytes()
+}
+
+/// Reads one simple-string, error or bulk-string reply.
+fn read_reply(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let line = line.trim_end();
+    match line.chars().next() {
+        Some('+') => Ok(line[1..].to_string()),
+        Some('-') => Err(anyhow::anyhow!("Redis error: {}", &line[1..])),
+        Some('$') => {
+            let len: usize = line[1..].parse()?;
+            let mut body = vec![0; len + 2];
+            reader.read_exact(&mut body)?;
+            body.truncate(len);
+            Ok(String::from_utf8_lossy(&body).to_string())
+        }
+        _ => Err(anyhow::anyhow!("Unexpected Redis reply: {:?}", line)),
+    }
+}
+
+fn parse_info(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.to_string(), v.trim().to_string()))
+        .collect()
+}
+
+pub fn analyze_redis_info(info: &HashMap<String, String>, status: &mut Status, details: &mut Vec<String>) {
+    let number = |key: &str| info.get(key).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+
+    details.push(format!("Redis {}: {} used{}",
+        info.get("redis_version").map(|v| v.as_str()).unwrap_or("(unknown version)"),
+        info.get("used_memory_human").map(|v| v.as_str()).unwrap_or("?"),
+        match number("maxmemory") {
+            0 => " (no memory limit)".to_string(),
+            _ => format!(" of {} ({} policy)", info.get("maxmemory_human").map(|v| v.as_str()).unwrap_or("?"),
+                info.get("maxmemory_policy").map(|v| v.as_str()).unwrap_or("?")),
+        }));
+
+    let (hits, misses) = (number("keyspace_hits"), number("keyspace_misses"));
+    if hits + misses > 0 {
+        let ratio = hits as f64 / (hits + misses) as f64;
+        details.push(format!("Redis hit ratio: {:.1}% ({} hits, {} misses)", ratio * 100.0, hits, misses));
+        if hits + misses >= MIN_LOOKUPS && ratio < MIN_HIT_RATIO {
+            status.escalate(Status::Warning);
+            details.push(format!("Warning: Redis hit ratio is below {:.0}%.", MIN_HIT_RATIO * 100.0));
+        }
+    }
+
+    let (used, max) = (number("used_memory"), number("maxmemory"));
+    if max > 0 && used * 10 > max * 9 {
+        status.escalate(Status::Warning);
+        details.push("Warning: Redis memory is over 90% of maxmemory.".to_string());
+    }
+    if number("evicted_keys") > 0 {
+        details.push(format!("Note: Redis has evicted {} keys for lack of memory.", number("evicted_keys")));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_detect_provider() {
+        assert_eq!(detect_provider("<?php\n/*\nPlugin Name: Redis Object Cache Drop-In\n*/"), "Redis");
+        assert_eq!(detect_provider("<?php $this->mc = new Memcached();"), "Memcached");
+        assert_eq!(detect_provider("<?php return apcu_fetch($key);"), "APCu");
+        assert_eq!(detect_provider("<?php // LiteSpeed Cache object cache"), "LiteSpeed");
+    }
+
+    #[test]
+    fn test_redis_info_against_server() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let server = std::thread::spawn(move || -> Result<Vec<u8>> {
+            let (mut stream, _) = listener.accept()?;
+            let mut request = vec![0; 64];
+            let n = stream.read(&mut request)?;
+            request.truncate(n);
+            let body = "# Server\r\nredis_version:7.2.4\r\n# Memory\r\nused_memory:950\r\nused_memory_human:950B\r\n\
+                maxmemory:1000\r\nmaxmemory_human:1000B\r\nmaxmemory_policy:allkeys-lru\r\n\
+                # Stats\r\nkeyspace_hits:600\r\nkeyspace_misses:400\r\nevicted_keys:0\r\n";
+            stream.write_all(format!("${}\r\n{}\r\n", body.len(), body).as_bytes())?;
+            Ok(request)
+        });
+
+        let info = redis_info(&RedisTarget::Tcp(addr.to_string()), &[], Duration::from_secs(5))?;
+        assert_eq!(server.join().unwrap()?, b"*1\r\n$4\r\nINFO\r\n");
+        assert_eq!(info.get("redis_version").map(|v| v.as_str()), Some("7.2.4"));
+
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        analyze_redis_info(&info, &mut status, &mut details);
+        assert_eq!(status, Status::Warning);
+        assert_eq!(details[0], "Redis 7.2.4: 950B used of 1000B (allkeys-lru policy)");
+        assert!(details.iter().any(|d| d == "Redis hit ratio: 60.0% (600 hits, 400 misses)"));
+        assert!(details.iter().any(|d| d.contains("Redis memory is over 90% of maxmemory")));
+        Ok(())
+    }
+}
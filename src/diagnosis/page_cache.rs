@@ -0,0 +1,226 @@
+use crate::diagnosis::Status;
+use crate::wp::WpCli;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+
+/// Posts fetched in addition to the home page.
+const SAMPLE_POSTS: &str = "3";
+
+/// Response headers that say which cache answered, and whether it was a hit.
+const CACHE_HEADERS: &[(&str, &str)] = &[
+    ("cf-cache-status", "Cloudflare"),
+    ("x-litespeed-cache", "LiteSpeed"),
+    ("x-varnish", "Varnish"),
+    ("x-fastcgi-cache", "nginx fastcgi_cache"),
+    ("x-nginx-cache", "nginx"),
+    ("x-cache-status", "nginx"),
+    ("x-proxy-cache", "proxy cache"),
+    ("x-sucuri-cache", "Sucuri"),
+    ("x-kinsta-cache", "Kinsta"),
+    ("x-cache", "CDN/proxy"),
+];
+
+/// HTML comments page cache plugins append to cached pages.
+const PLUGIN_MARKERS: &[(&str, &str)] = &[
+    ("Cached page generated by WP-Super-Cache", "WP Super Cache"),
+    ("This website is like a Rocket", "WP Rocket"),
+    ("Performance optimized by W3 Total Cache", "W3 Total Cache"),
+    ("Cache Enabler by KeyCDN", "Cache Enabler"),
+    ("Page cached by LiteSpeed Cache", "LiteSpeed Cache"),
+    ("WP Fastest Cache file was created", "WP Fastest Cache"),
+];
+
+/// One request as seen by an anonymous visitor.
+#[derive(Debug)]
+pub struct Fetch {
+    pub status: u16,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    /// Time until the response headers arrived.
+    pub ttfb: Duration,
+}
+
+impl Fetch {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// What two requests to the same page tell about its caching.
+#[derive(Debug, PartialEq)]
+pub struct PageResult {
+    pub layers: Vec<String>,
+    pub hit: bool,
+    pub notes: Vec<String>,
+}
+
+pub fn check(wp: &WpCli, root: &Path, home: &str, status: &mut Status, details: &mut Vec<String>) {
+    let mut urls = vec![format!("{}/", home.trim_end_matches('/'))];
+    if let Ok(posts) = wp.run(&["post", "list", "--post_type=post", "--post_status=publish",
+        &format!("--posts_per_page={}", SAMPLE_POSTS), "--field=url"], root) {
+        urls.extend(posts.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()));
+    }
+
+    let client = match reqwest::blocking::Client::builder().timeout(Duration::from_secs(30)).build() {
+        Ok(client) => client,
+        Err(e) => {
+            details.push(format!("Could not create HTTP client: {}", e));
+            return;
+        }
+    };
+
+    let mut results = Vec::new();
+    for url in urls {
+        // The first request warms the cache, the second should be served from it
+        match fetch(&client, &url).and_then(|cold| Ok((cold, fetch(&client, &url)?))) {
+            Ok((cold, warm)) => results.push((url, cold.ttfb, warm.ttfb, inspect(&cold, &warm))),
+            Err(e) => details.push(format!("Could not fetch {}: {}", url, e)),
+        }
+    }
+    analyze(&results, status, details);
+}
+
+pub fn fetch(client: &reqwest::blocking::Client, url: &str) -> Result<Fetch> {
+    let started = Instant::now();
+    let response = client.get(url).send()?;
+    let ttfb = started.elapsed();
+
+    let status = response.status().as_u16();
+    let headers = response.headers().iter()
+        .map(|(name, value)| (name.as_str().to_lowercase(), String::from_utf8_lossy(value.as_bytes()).to_string()))
+        .collect();
+    Ok(Fetch { status, headers, body: response.text()?, ttfb })
+}
+
+/// Compares a cold and a warm request to the same page.
+pub fn inspect(cold: &Fetch, warm: &Fetch) -> PageResult {
+    let mut layers = Vec::new();
+    let mut hit = false;
+    let mut notes = Vec::new();
+
+    for (header, layer) in CACHE_HEADERS {
+        if let Some(value) = warm.header(header) {
+            if !layers.iter().any(|l| l == layer) {
+                layers.push(layer.to_string());
+            }
+            let upper = value.to_uppercase();
+            // Varnish sends two transaction ids on a hit
+            hit |= upper.contains("HIT") || (*header == "x-varnish" && value.split_whitespace().count() > 1);
+        }
+    }
+    if warm.header("via").is_some_and(|v| v.to_lowercase().contains("varnish")) && !layers.iter().any(|l| l == "Varnish") {
+        layers.push("Varnish".to_string());
+    }
+    if warm.header("age").and_then(|a| a.trim().parse::<u64>().ok()).is_some_and(|age| age > 0) {
+        hit = true;
+    }
+    for (marker, plugin) in PLUGIN_MARKERS {
+        if warm.body.contains(marker) {
+            layers.push(format!("{} (plugin)", plugin));
+            hit = true;
+        }
+    }
+
+    if let Some(cache_control) = warm.header("cache-control") {
+        let lower = cache_control.to_lowercase();
+        if ["no-store", "no-cache", "private", "max-age=0"].iter().any(|d| lower.contains(d)) {
+            notes.push(format!("Cache-Control: {}", cache_control));
+        }
+    }
+    if cold.header("set-cookie").is_some() {
+        notes.push("sets a cookie for anonymous visitors (most caches skip such pages)".to_string());
+    }
+    if warm.status != 200 {
+        notes.push(format!("returned HTTP {}", warm.status));
+    }
+
+    PageResult { layers, hit, notes }
+}
+
+pub fn analyze(results: &[(String, Duration, Duration, PageResult)], status: &mut Status, details: &mut Vec<String>) {
+    if results.is_empty() {
+        return;
+    }
+
+    let mut layers: Vec<&str> = results.iter().flat_map(|(_, _, _, r)| r.layers.iter().map(|l| l.as_str())).collect();
+    layers.sort();
+    layers.dedup();
+    let hits = results.iter().filter(|(_, _, _, r)| r.hit).count();
+
+    let summary = if hits == results.len() {
+        let source = if layers.is_empty() { "Age header".to_string() } else { layers.join(", ") };
+        format!("Page cache is effective: {}/{} pages served from cache ({}).", hits, results.len(), source)
+    } else if layers.is_empty() {
+        status.escalate(Status::Warning);
+        "Warning: No page cache detected (no cache headers, Age or cache plugin markers on repeated requests).".to_string()
+    } else {
+        status.escalate(Status::Warning);
+        format!("Warning: Page cache present ({}) but only {}/{} pages were served from cache on a repeated request.",
+            layers.join(", "), hits, results.len())
+    };
+    details.push(summary);
+
+    for (url, cold, warm, result) in results {
+        details.push(format!(" - {}: {} (TTFB cold {} ms, warm {} ms){}", url,
+            if result.hit { "cached" } else { "not cached" },
+            cold.as_millis(), warm.as_millis(),
+            if result.notes.is_empty() { String::new() } else { format!(", {}", result.notes.join(", ")) }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_fetch_and_inspect_against_server() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let server = std::thread::spawn(move || -> Result<()> {
+            for (cache, age) in [("MISS", "0"), ("HIT", "12")] {
+                let (mut stream, _) = listener.accept()?;
+                let mut request = vec![0; 1024];
+                let _ = stream.read(&mut request)?;
+                let body = "<html>Hello</html>";
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nX-Cache: {}\r\nAge: {}\r\n\
+                    Cache-Control: public, max-age=600\r\nConnection: close\r\n\r\n{}", body.len(), cache, age, body)?;
+            }
+            Ok(())
+        });
+
+        let client = reqwest::blocking::Client::new();
+        let url = format!("http://{}/", addr);
+        let cold = fetch(&client, &url)?;
+        let warm = fetch(&client, &url)?;
+        server.join().unwrap()?;
+
+        assert_eq!(cold.header("x-cache"), Some("MISS"));
+        let result = inspect(&cold, &warm);
+        assert_eq!(result, PageResult { layers: vec!["CDN/proxy".to_string()], hit: true, notes: vec![] });
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_without_page_cache() {
+        let uncached = Fetch {
+            status: 200,
+            headers: vec![("cache-control".to_string(), "no-cache, must-revalidate".to_string())],
+            body: "<html></html>".to_string(),
+            ttfb: Duration::from_millis(800),
+        };
+        let result = inspect(&uncached, &uncached);
+        let results = vec![("https://example.com/".to_string(), Duration::from_millis(820), Duration::from_millis(800), result)];
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+
+        analyze(&results, &mut status, &mut details);
+
+        assert_eq!(status, Status::Warning);
+        assert!(details[0].starts_with("Warning: No page cache detected"));
+        assert_eq!(details[1], " - https://example.com/: not cached (TTFB cold 820 ms, warm 800 ms), Cache-Control: no-cache, must-revalidate");
+    }
+}
@@ -1,7 +1,16 @@
-use crate::diagnosis::{Diagnosis, DiagnosisReport, Status};
+use crate::diagnosis::{Diagnosis, DiagnosisReport, SiteUrls, Status};
+use crate::diagnosis::{cron, object_cache, page_cache};
 use crate::wp::WpCli;
+use std::collections::HashMap;
 use std::path::Path;
 use anyhow::Result;
+use serde_json::Value;
+
+/// Plugins whose hook callbacks take longer than this in total are reported.
+const SLOW_PLUGIN_MS: f64 = 100.0;
+
+/// Number of plugins listed in the profile breakdown.
+const TOP_PLUGINS: usize = 5;
 
 pub struct PerformanceDiagnosis;
 
@@ -30,25 +39,32 @@ impl Diagnosis for PerformanceDiagnosis {
 
         // 2. Check Cron
         println!("    > Checking cron events...");
-        // Simpler cron check: count overdue
-        match wp.run(&["cron", "event", "list", "--format=csv"], root) {
-             Ok(_) => {
-                 details.push("Cron events checked.".to_string());
-             }
-             Err(_) => details.push("Could not check cron events.".to_string()),
-        }
+        cron::check(wp, root, &mut overall_status, &mut details);
 
         // 3. Object Cache
         println!("    > Checking object cache...");
-        match wp.run(&["plugin", "is-active", "redis-cache"], root) {
-            Ok(_) => details.push("Redis Object Cache plugin is active.".to_string()),
-            Err(_) => {
-                // Check for other drops ins?
-                 match wp.run(&["plugin", "is-active", "w3-total-cache"], root) {
-                     Ok(_) => details.push("W3 Total Cache is active.".to_string()),
-                     Err(_) => details.push("No common object cache plugin detected (Redis/W3TC).".to_string()),
-                 }
+        object_cache::check(wp, root, &mut overall_status, &mut details);
+
+        // 4. Page cache
+        println!("    > Checking page cache...");
+        match SiteUrls::fetch(wp, root) {
+            Ok(urls) => page_cache::check(wp, root, &urls.home, &mut overall_status, &mut details),
+            Err(e) => details.push(format!("Could not determine the home URL: {}", e)),
+        }
+
+        // 5. Profile plugins (needs the wp-cli/profile-command package)
+        println!("    > Profiling page load...");
+        if wp.run(&["cli", "has-command", "profile stage"], root).is_ok() {
+            match wp.run(&["profile", "stage", "--fields=stage,time,query_time,query_count", "--format=json"], root) {
+                Ok(json) => self.analyze_stages(&json, &mut details),
+                Err(e) => details.push(format!("Could not profile load stages: {}", e)),
+            }
+            match wp.run(&["profile", "hook", "--all", "--fields=callback,location,time,query_count", "--format=json"], root) {
+                Ok(json) => self.analyze_hooks(&json, &mut overall_status, &mut details),
+                Err(e) => details.push(format!("Could not profile hooks: {}", e)),
             }
+        } else {
+            details.push("Note: Install the profile command (`wp package install wp-cli/profile-command`) for per-plugin timings.".to_string());
         }
 
         Ok(DiagnosisReport {
@@ -71,6 +87,75 @@ impl PerformanceDiagnosis {
             details.push("Warning: Autoloaded options size is high (> 1MB).".to_string());
         }
     }
+
+    fn analyze_stages(&self, json_output: &str, details: &mut Vec<String>) {
+        let stages: Vec<Value> = serde_json::from_str(json_output).unwrap_or_default();
+        let parts: Vec<String> = stages.iter()
+            .filter(|s| s["stage"].is_string())
+            .map(|s| format!("{} {:.0} ms ({} queries)",
+                s["stage"].as_str().unwrap_or_default(), seconds(&s["time"]) * 1000.0, count(&s["query_count"])))
+            .collect();
+        if !parts.is_empty() {
+            details.push(format!("Load stages: {}", parts.join(", ")));
+        }
+    }
+
+    /// Attributes hook callback time to the plugin each callback is defined in.
+    fn analyze_hooks(&self, json_output: &str, status: &mut Status, details: &mut Vec<String>) {
+        let callbacks: Vec<Value> = serde_json::from_str(json_output).unwrap_or_default();
+        let mut totals: HashMap<String, (f64, u64)> = HashMap::new();
+        for callback in &callbacks {
+            if let Some(plugin) = callback["location"].as_str().and_then(plugin_from_location) {
+                let entry = totals.entry(plugin).or_default();
+                entry.0 += seconds(&callback["time"]) * 1000.0;
+                entry.1 += count(&callback["query_count"]);
+            }
+        }
+
+        let mut ranked: Vec<(String, (f64, u64))> = totals.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.0.total_cmp(&a.1.0));
+        if ranked.is_empty() {
+            details.push("No plugin hook callbacks were profiled.".to_string());
+            return;
+        }
+
+        details.push("Slowest plugins (hook callbacks):".to_string());
+        for (plugin, (ms, queries)) in ranked.iter().take(TOP_PLUGINS) {
+            details.push(format!(" - {}: {:.0} ms, {} queries", plugin, ms, queries));
+        }
+        for (plugin, (ms, _)) in ranked.iter().filter(|(_, (ms, _))| *ms > SLOW_PLUGIN_MS) {
+            status.escalate(Status::Warning);
+            details.push(format!("Warning: Plugin '{}' adds {:.0} ms to every page load (> {:.0} ms).", plugin, ms, SLOW_PLUGIN_MS));
+        }
+    }
+}
+
+/// `wp profile` prints times as strings like "0.2335s".
+fn seconds(value: &Value) -> f64 {
+    match value {
+        Value::String(s) => s.trim().trim_end_matches('s').parse().unwrap_or(0.0),
+        other => other.as_f64().unwrap_or(0.0),
+    }
+}
+
+fn count(value: &Value) -> u64 {
+    match value {
+        Value::String(s) => s.trim().parse().unwrap_or(0),
+        other => other.as_u64().unwrap_or(0),
+    }
+}
+
+/// "wp-content/plugins/akismet/class.akismet.php:123" -> "akismet".
+/// mu-plugins are prefixed so they are not mixed up with regular plugins.
+fn plugin_from_location(location: &str) -> Option<String> {
+    for (marker, prefix) in [("mu-plugins/", "mu-plugins/"), ("plugins/", "")] {
+        if let Some(idx) = location.find(marker) {
+            let rest = &location[idx + marker.len()..];
+            let name = rest.split(['/', ':']).next().filter(|n| !n.is_empty())?;
+            return Some(format!("{}{}", prefix, name.trim_end_matches(".php")));
+        }
+    }
+    None
 }
 
 #[cfg(test)]
@@ -91,6 +176,27 @@ mod tests {
         assert!(details.iter().any(|d| d.contains("Autoloaded options size: 2.00 MB")));
     }
 
+    #[test]
+    fn test_analyze_hooks_attributes_time_to_plugins() {
+        let json = r#"[
+            {"callback": "Akismet::init()", "location": "wp-content/plugins/akismet/class.akismet.php:42", "time": "0.0123s", "query_count": 1},
+            {"callback": "heavy_init()", "location": "wp-content/plugins/heavy-slider/heavy.php:10", "time": "0.0900s", "query_count": 12},
+            {"callback": "heavy_footer()", "location": "wp-content/plugins/heavy-slider/inc/footer.php:5", "time": "0.0400s", "query_count": "3"},
+            {"callback": "wp_head()", "location": "wp-includes/general-template.php:3000", "time": "0.5000s", "query_count": 0},
+            {"callback": "loader()", "location": "wp-content/mu-plugins/loader.php:3", "time": "0.0010s", "query_count": 0}
+        ]"#;
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+
+        PerformanceDiagnosis.analyze_hooks(json, &mut status, &mut details);
+
+        assert_eq!(status, Status::Warning);
+        assert_eq!(details[1], " - heavy-slider: 130 ms, 15 queries");
+        assert_eq!(details[2], " - akismet: 12 ms, 1 queries");
+        assert!(details.iter().any(|d| d == " - mu-plugins/loader: 1 ms, 0 queries"));
+        assert!(details.iter().any(|d| d.contains("Warning: Plugin 'heavy-slider' adds 130 ms")));
+    }
+
     #[test]
     fn test_analyze_autoload_size_small() {
         let diagnosis = PerformanceDiagnosis;
@@ -0,0 +1,156 @@
+use crate::diagnosis::Status;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Paths listed per finding before the rest is summarised.
+const MAX_LISTED: usize = 10;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Issue {
+    WorldWritable,
+    ConfigReadable,
+    ExecutableUpload,
+    WrongOwner,
+}
+
+impl Issue {
+    fn describe(&self) -> &'static str {
+        match self {
+            Issue::WorldWritable => "world-writable",
+            Issue::ConfigReadable => "wp-config.php is readable by other users",
+            Issue::ExecutableUpload => "executable files in uploads",
+            Issue::WrongOwner => "owned by a different user than the site owner",
+        }
+    }
+
+    /// Issues a chmod to `target_mode` fixes. Ownership needs a chown and is left alone.
+    pub fn fixable(&self) -> bool {
+        *self != Issue::WrongOwner
+    }
+}
+
+/// A file or directory with a permission problem, relative to the WordPress root.
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub path: String,
+    pub issue: Issue,
+    pub mode: u32,
+}
+
+/// Walks the WordPress root. Symlinks are not followed, their modes mean nothing.
+pub fn scan(root: &Path, owner: Option<u32>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+        if entry.file_type().is_symlink() {
+            continue;
+        }
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        let rel = match entry.path().strip_prefix(root) {
+            Ok(rel) if rel.as_os_str().is_empty() => ".".to_string(),
+            Ok(rel) => rel.to_string_lossy().to_string(),
+            Err(_) => continue,
+        };
+        let mode = metadata.mode() & 0o7777;
+        let mut push = |issue| findings.push(Finding { path: rel.clone(), issue, mode });
+
+        if mode & 0o002 != 0 {
+            push(Issue::WorldWritable);
+        }
+        if rel == "wp-config.php" && mode & 0o004 != 0 {
+            push(Issue::ConfigReadable);
+        }
+        if metadata.is_file() && mode & 0o111 != 0 && rel.starts_with("wp-content/uploads/") {
+            push(Issue::ExecutableUpload);
+        }
+        if owner.is_some_and(|owner| metadata.uid() != owner) {
+            push(Issue::WrongOwner);
+        }
+    }
+    findings
+}
+
+/// The mode we normalise to: 755 for directories, 644 for files, and 600 for wp-config.php,
+/// or 640 when the group can already read it (the web server often reads it through the group).
+/// Directories keep their setgid and sticky bits, shared hosting relies on them for group ownership.
+pub fn target_mode(rel: &str, is_dir: bool, current: u32) -> u32 {
+    if is_dir {
+        0o755 | (current & 0o7000)
+    } else if rel == "wp-config.php" {
+        if current & 0o040 != 0 { 0o640 } else { 0o600 }
+    } else {
+        0o644
+    }
+}
+
+pub fn analyze(findings: &[Finding], status: &mut Status, details: &mut Vec<String>) {
+    if findings.is_empty() {
+        details.push("File permissions: OK".to_string());
+        return;
+    }
+
+    for issue in [Issue::WorldWritable, Issue::ConfigReadable, Issue::ExecutableUpload, Issue::WrongOwner] {
+        let matching: Vec<&Finding> = findings.iter().filter(|f| f.issue == issue).collect();
+        if matching.is_empty() {
+            continue;
+        }
+        status.escalate(Status::Warning);
+        details.push(format!("Warning: {} {}:", matching.len(), issue.describe()));
+        for finding in matching.iter().take(MAX_LISTED) {
+            details.push(format!("   {:o} {}", finding.mode, finding.path));
+        }
+        if matching.len() > MAX_LISTED {
+            details.push(format!("   ... and {} more", matching.len() - MAX_LISTED));
+        }
+    }
+
+    if findings.iter().any(|f| f.issue.fixable()) {
+        details.push("Run `wp-agent fix` to normalise permissions to 644/755 (600/640 for wp-config.php).".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use anyhow::Result;
+
+    #[test]
+    fn test_scan_and_analyze_permissions() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        let uploads = root.path().join("wp-content/uploads");
+        fs::create_dir_all(&uploads)?;
+        for (file, mode) in [("wp-config.php", 0o644), ("index.php", 0o644), ("wp-content/uploads/shell.php", 0o755)] {
+            fs::write(root.path().join(file), "<?php")?;
+            fs::set_permissions(root.path().join(file), fs::Permissions::from_mode(mode))?;
+        }
+        fs::set_permissions(&uploads, fs::Permissions::from_mode(0o777))?;
+
+        let owner = fs::metadata(root.path())?.uid();
+        let mut findings = scan(root.path(), Some(owner));
+        findings.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(findings, vec![
+            Finding { path: "wp-config.php".to_string(), issue: Issue::ConfigReadable, mode: 0o644 },
+            Finding { path: "wp-content/uploads".to_string(), issue: Issue::WorldWritable, mode: 0o777 },
+            Finding { path: "wp-content/uploads/shell.php".to_string(), issue: Issue::ExecutableUpload, mode: 0o755 },
+        ]);
+        assert_eq!(target_mode("wp-config.php", false, 0o644), 0o640);
+        assert_eq!(target_mode("wp-config.php", false, 0o604), 0o600);
+        assert_eq!(target_mode("wp-content/uploads", true, 0o777), 0o755);
+        assert_eq!(target_mode("wp-content/uploads", true, 0o2777), 0o2755);
+
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        analyze(&findings, &mut status, &mut details);
+        assert_eq!(status, Status::Warning);
+        assert_eq!(details[0], "Warning: 1 world-writable:");
+        assert_eq!(details[1], "   777 wp-content/uploads");
+        assert!(details.last().unwrap().starts_with("Run `wp-agent fix`"));
+        Ok(())
+    }
+}
@@ -1,15 +1,55 @@
-use crate::diagnosis::{Diagnosis, DiagnosisReport, Status};
+use crate::diagnosis::{self, Diagnosis, DiagnosisReport, Status};
+use crate::fsutil;
 use crate::wp::WpCli;
+use crate::wporg::{PluginInfo, PluginMetadata};
+use std::collections::HashMap;
+use std::fs;
 use std::path::Path;
 use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use serde::Deserialize;
 
+/// Drop-ins WordPress loads from wp-content (see `_get_dropins()`).
+const DROPINS: &[&str] = &[
+    "advanced-cache.php", "db.php", "db-error.php", "install.php", "maintenance.php",
+    "object-cache.php", "php-error.php", "fatal-error-handler.php", "sunrise.php",
+    "blog-deleted.php", "blog-inactive.php", "blog-suspended.php",
+];
+
+/// Files modified more recently than this are flagged.
+const RECENT_DAYS: i64 = 7;
+
+/// Plugins not updated on wordpress.org for this long are considered abandoned.
+const ABANDONED_DAYS: i64 = 2 * 365;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum ExtraKind {
+    MustUse,
+    DropIn,
+    /// A PHP file in wp-content that WordPress does not load by itself.
+    Unknown,
+}
+
+/// A must-use plugin, drop-in or stray PHP file found on disk.
+#[derive(Debug)]
+struct ExtraFile {
+    kind: ExtraKind,
+    path: String,
+    name: Option<String>,
+    version: Option<String>,
+    author: Option<String>,
+    sha256: String,
+    modified: DateTime<Utc>,
+}
+
 #[derive(Deserialize, Debug)]
-struct Plugin {
-    name: String,
-    status: String,
-    update: String, // "available" or "none"
-    version: String,
+pub(crate) struct Plugin {
+    pub(crate) name: String,
+    pub(crate) status: String,
+    pub(crate) update: String, // "available" or "none"
+    pub(crate) version: String,
+    #[serde(default)]
+    pub(crate) update_version: Option<String>,
 }
 
 pub struct PluginDiagnosis;
@@ -21,7 +61,32 @@ impl Diagnosis for PluginDiagnosis {
         println!("    > Fetching plugin list...");
         let output = wp.run(&["plugin", "list", "--format=json"], root)?;
         
-        self.analyze_plugins(&output)
+        let mut report = self.analyze_plugins(&output)?;
+
+        println!("    > Checking must-use plugins and drop-ins...");
+        match inventory(&root.join("wp-content")) {
+            Ok(files) => self.analyze_extras(&files, Utc::now(), &mut report.status, &mut report.details),
+            Err(e) => report.details.push(format!("Could not inspect mu-plugins and drop-ins: {}", e)),
+        }
+
+        println!("    > Checking wordpress.org plugin metadata...");
+        let plugins: Vec<Plugin> = serde_json::from_str(&output)?;
+        let wp_version = wp.run(&["core", "version"], root).map(|v| v.trim().to_string()).unwrap_or_default();
+        match PluginMetadata::load() {
+            Ok(mut metadata) => {
+                let infos: HashMap<String, PluginInfo> = plugins.iter()
+                    .filter_map(|p| metadata.get(&p.name).map(|i| (p.name.clone(), i.clone())))
+                    .collect();
+                if let Err(e) = metadata.save() {
+                    report.details.push(format!("Could not save plugin metadata cache: {}", e));
+                }
+                let today = Utc::now().date_naive();
+                self.analyze_metadata(&plugins, &infos, &wp_version, today, &mut report.status, &mut report.details);
+            }
+            Err(e) => report.details.push(format!("Could not load plugin metadata: {}", e)),
+        }
+
+        Ok(report)
     }
 }
 
@@ -61,6 +126,142 @@ impl PluginDiagnosis {
             details,
         })
     }
+
+    fn analyze_metadata(&self, plugins: &[Plugin], infos: &HashMap<String, PluginInfo>, wp_version: &str,
+        today: NaiveDate, status: &mut Status, details: &mut Vec<String>) {
+        let mut unchecked = 0;
+        let mut not_hosted = 0;
+
+        for p in plugins {
+            let info = match infos.get(&p.name) {
+                Some(info) => info,
+                None => {
+                    unchecked += 1;
+                    continue;
+                }
+            };
+
+            if info.not_found {
+                not_hosted += 1;
+            } else if info.closed {
+                let reason = info.closed_reason.as_deref().unwrap_or("no reason given");
+                let level = if reason.to_lowercase().contains("security") { Status::Error } else { Status::Warning };
+                status.escalate(level);
+                details.push(format!("{}: Plugin '{}' was closed on wordpress.org{} ({}). It will not receive updates.",
+                    if level == Status::Error { "Critical" } else { "Warning" },
+                    p.name,
+                    info.closed_date.as_deref().map(|d| format!(" on {}", d)).unwrap_or_default(),
+                    reason));
+            } else if let Some(updated) = info.last_updated_date().filter(|d| today - *d > Duration::days(ABANDONED_DAYS)) {
+                status.escalate(Status::Warning);
+                details.push(format!("Warning: Plugin '{}' looks abandoned: last updated on wordpress.org {} ({} years ago).",
+                    p.name, updated, (today - updated).num_days() / 365));
+            }
+
+            if let Some(tested) = info.tested.as_deref() {
+                let behind = release_index(wp_version) - release_index(tested);
+                if !wp_version.is_empty() && behind >= 3 {
+                    details.push(format!("Note: Plugin '{}' was only tested up to WordPress {} ({} major releases behind {}).",
+                        p.name, tested, behind, wp_version));
+                }
+            }
+        }
+
+        if not_hosted > 0 {
+            details.push(format!("{} plugins are not hosted on wordpress.org (premium or custom), maintenance status unknown.", not_hosted));
+        }
+        if unchecked > 0 {
+            details.push(format!("{} plugins could not be checked against wordpress.org (offline and not cached).", unchecked));
+        }
+    }
+
+    fn analyze_extras(&self, files: &[ExtraFile], now: DateTime<Utc>, status: &mut Status, details: &mut Vec<String>) {
+        for (kind, title) in [(ExtraKind::MustUse, "Must-use plugins"), (ExtraKind::DropIn, "Drop-ins")] {
+            let group: Vec<&ExtraFile> = files.iter().filter(|f| f.kind == kind).collect();
+            if group.is_empty() {
+                continue;
+            }
+            details.push(format!("{} ({}):", title, group.len()));
+            for f in group {
+                details.push(format!(" - {}: {} {} by {} [sha256 {}, modified {}]",
+                    f.path,
+                    f.name.as_deref().unwrap_or("(no header)"),
+                    f.version.as_deref().unwrap_or("-"),
+                    f.author.as_deref().unwrap_or("unknown"),
+                    f.sha256,
+                    f.modified.format("%Y-%m-%d %H:%M UTC")));
+            }
+        }
+
+        for f in files {
+            match f.kind {
+                ExtraKind::Unknown => {
+                    status.escalate(Status::Warning);
+                    details.push(format!("Warning: Unexpected PHP file in wp-content: {}", f.path));
+                }
+                _ if f.name.is_none() => {
+                    status.escalate(Status::Warning);
+                    details.push(format!("Warning: {} has no plugin header, its origin is unknown.", f.path));
+                }
+                _ => {}
+            }
+            let age = now - f.modified;
+            if age < Duration::days(RECENT_DAYS) {
+                status.escalate(Status::Warning);
+                details.push(format!("Warning: {} was modified {} hours ago.", f.path, age.num_hours().max(0)));
+            }
+        }
+    }
+}
+
+/// Major releases go 5.8, 5.9, 6.0, ... so "major * 10 + minor" counts them.
+fn release_index(version: &str) -> i64 {
+    let mut parts = version.split('.').map(|p| p.parse::<i64>().unwrap_or(0));
+    let major = parts.next().unwrap_or(0);
+    let minor = parts.next().unwrap_or(0);
+    major * 10 + minor
+}
+
+/// Lists mu-plugins (top-level PHP files only, like WordPress), drop-ins and any other PHP file
+/// directly in wp-content.
+fn inventory(content_dir: &Path) -> Result<Vec<ExtraFile>> {
+    let mut files = Vec::new();
+
+    let mu_dir = content_dir.join("mu-plugins");
+    if mu_dir.is_dir() {
+        for entry in fs::read_dir(&mu_dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|e| e == "php") {
+                files.push(describe(ExtraKind::MustUse, &path, content_dir)?);
+            }
+        }
+    }
+
+    for entry in fs::read_dir(content_dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if !path.is_file() || !name.ends_with(".php") || name == "index.php" {
+            continue;
+        }
+        let kind = if DROPINS.contains(&name.as_str()) { ExtraKind::DropIn } else { ExtraKind::Unknown };
+        files.push(describe(kind, &path, content_dir)?);
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn describe(kind: ExtraKind, path: &Path, content_dir: &Path) -> Result<ExtraFile> {
+    let content = String::from_utf8_lossy(&fs::read(path)?).to_string();
+    Ok(ExtraFile {
+        kind,
+        path: path.strip_prefix(content_dir)?.to_string_lossy().to_string(),
+        name: diagnosis::file_header(&content, "Plugin Name"),
+        version: diagnosis::file_header(&content, "Version"),
+        author: diagnosis::file_header(&content, "Author"),
+        sha256: fsutil::sha256_file(path)?,
+        modified: DateTime::<Utc>::from(fs::metadata(path)?.modified()?),
+    })
 }
 
 #[cfg(test)]
@@ -84,6 +285,73 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_analyze_metadata_flags_closed_and_abandoned() -> Result<()> {
+        let plugins: Vec<Plugin> = serde_json::from_str(r#"[
+            {"name": "old-slider", "status": "active", "update": "none", "version": "1.0"},
+            {"name": "bad-forms", "status": "active", "update": "none", "version": "2.0"},
+            {"name": "akismet", "status": "active", "update": "none", "version": "5.3"},
+            {"name": "premium", "status": "active", "update": "none", "version": "1.0"}
+        ]"#)?;
+        let mut infos = HashMap::new();
+        infos.insert("old-slider".to_string(), PluginInfo {
+            last_updated: Some("2019-05-01 1:00pm GMT".to_string()), tested: Some("5.2".to_string()), ..Default::default()
+        });
+        infos.insert("bad-forms".to_string(), PluginInfo {
+            closed: true, closed_date: Some("2024-01-02".to_string()), closed_reason: Some("Security Issue".to_string()), ..Default::default()
+        });
+        infos.insert("akismet".to_string(), PluginInfo {
+            last_updated: Some("2024-03-01 9:00am GMT".to_string()), tested: Some("6.4".to_string()), ..Default::default()
+        });
+        infos.insert("premium".to_string(), PluginInfo { not_found: true, ..Default::default() });
+
+        let diagnosis = PluginDiagnosis;
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        diagnosis.analyze_metadata(&plugins, &infos, "6.5", today, &mut status, &mut details);
+
+        assert_eq!(status, Status::Error);
+        assert!(details.iter().any(|d| d.contains("Critical: Plugin 'bad-forms' was closed on wordpress.org on 2024-01-02 (Security Issue)")));
+        assert!(details.iter().any(|d| d.contains("Plugin 'old-slider' looks abandoned: last updated on wordpress.org 2019-05-01 (5 years ago)")));
+        assert!(details.iter().any(|d| d.contains("'old-slider' was only tested up to WordPress 5.2")));
+        assert!(!details.iter().any(|d| d.contains("'akismet'")));
+        assert!(details.iter().any(|d| d.contains("1 plugins are not hosted on wordpress.org")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_inventory_flags_unknown_and_recent_files() -> Result<()> {
+        let content = tempfile::tempdir()?;
+        fs::create_dir_all(content.path().join("mu-plugins/loader"))?;
+        fs::write(content.path().join("mu-plugins/loader.php"), "<?php\n/*\nPlugin Name: Loader\nVersion: 1.2\nAuthor: Acme\n*/")?;
+        fs::write(content.path().join("mu-plugins/loader/ignored.php"), "<?php")?;
+        fs::write(content.path().join("object-cache.php"), "<?php\n/*\nPlugin Name: Redis Object Cache Drop-In\n*/")?;
+        fs::write(content.path().join("wp-tmp.php"), "<?php eval($_POST['x']);")?;
+        fs::write(content.path().join("index.php"), "<?php // Silence is golden.")?;
+
+        let files = inventory(content.path())?;
+        assert_eq!(files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+            vec!["mu-plugins/loader.php", "object-cache.php", "wp-tmp.php"]);
+        assert_eq!(files[0].version.as_deref(), Some("1.2"));
+        assert_eq!(files[1].kind, ExtraKind::DropIn);
+
+        let diagnosis = PluginDiagnosis;
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        diagnosis.analyze_extras(&files, files[0].modified + Duration::days(30), &mut status, &mut details);
+
+        assert_eq!(status, Status::Warning);
+        assert!(details.iter().any(|d| d.starts_with(" - mu-plugins/loader.php: Loader 1.2 by Acme [sha256 ")));
+        assert!(details.iter().any(|d| d == "Warning: Unexpected PHP file in wp-content: wp-tmp.php"));
+        assert!(!details.iter().any(|d| d.contains("hours ago")));
+
+        let mut details = Vec::new();
+        diagnosis.analyze_extras(&files, files[0].modified, &mut status, &mut details);
+        assert!(details.iter().any(|d| d.contains("object-cache.php was modified")));
+        Ok(())
+    }
+
     #[test]
     fn test_analyze_plugins_all_ok() -> Result<()> {
         let json = r#"[
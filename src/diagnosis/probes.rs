@@ -0,0 +1,215 @@
+use crate::diagnosis::tls;
+use crate::store;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// An external service the site depends on, read from `~/.wp-agent/probes.json`.
+///
+/// Supported schemes: `https`, `http`, `smtp` (expects a 220 banner), `smtps`
+/// (implicit TLS) and `tcp` (connect only).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Probe {
+    pub name: String,
+    pub url: String,
+    /// HTTP status the service should answer with. Any status below 500 when unset.
+    #[serde(default)]
+    pub expect_status: Option<u16>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+impl Probe {
+    fn new(name: &str, url: &str, expect_status: Option<u16>) -> Probe {
+        Probe { name: name.to_string(), url: url.to_string(), expect_status, timeout_secs: None }
+    }
+}
+
+/// What WordPress itself talks to: update checks and package downloads.
+pub fn default_probes() -> Vec<Probe> {
+    vec![
+        Probe::new("api.wordpress.org", "https://api.wordpress.org/core/version-check/1.7/", Some(200)),
+        Probe::new("downloads.wordpress.org", "https://downloads.wordpress.org/plugin/akismet.zip", None),
+    ]
+}
+
+/// Loads the configured probes, or the defaults when no config file exists.
+pub fn load() -> Result<Vec<Probe>> {
+    let path = store::data_dir()?.join("probes.json");
+    if !path.is_file() {
+        return Ok(default_probes());
+    }
+    serde_json::from_slice(&fs::read(&path)?)
+        .map_err(|e| anyhow::anyhow!("Invalid probe config {:?}: {}", path, e))
+}
+
+/// Timing of each phase of a probe. Phases that were not reached are `None`.
+#[derive(Debug)]
+pub struct ProbeResult {
+    pub dns: Option<Duration>,
+    pub connect: Option<Duration>,
+    pub tls: Option<Duration>,
+    /// Time from sending the request (or connecting, for SMTP) to the first response line.
+    pub response: Option<Duration>,
+    /// `Ok` with a short summary (e.g. "HTTP 200"), or why the probe failed.
+    pub outcome: Result<String, String>,
+}
+
+impl ProbeResult {
+    pub fn timings(&self) -> String {
+        let phases = [("dns", self.dns), ("connect", self.connect), ("tls", self.tls), ("response", self.response)];
+        phases.iter()
+            .filter_map(|(name, d)| d.map(|d| format!("{} {} ms", name, d.as_millis())))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+pub fn run(probe: &Probe) -> ProbeResult {
+    let mut result = ProbeResult { dns: None, connect: None, tls: None, response: None, outcome: Ok(String::new()) };
+    result.outcome = execute(probe, &mut result).map_err(|e| e.to_string());
+    result
+}
+
+fn execute(probe: &Probe, result: &mut ProbeResult) -> Result<String> {
+    let url = Url::parse(&probe.url)?;
+    let host = url.host_str().ok_or_else(|| anyhow::anyhow!("no host in {}", probe.url))?
+        .trim_start_matches('[').trim_end_matches(']').to_string();
+    let port = url.port_or_known_default().or(match url.scheme() {
+        "smtp" => Some(25),
+        "smtps" => Some(465),
+        _ => None,
+    }).ok_or_else(|| anyhow::anyhow!("no port in {}", probe.url))?;
+    let timeout = Duration::from_secs(probe.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+
+    let started = Instant::now();
+    let addr: SocketAddr = (host.as_str(), port).to_socket_addrs()
+        .map_err(|e| anyhow::anyhow!("DNS lookup failed: {}", e))?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("DNS lookup returned no addresses"))?;
+    result.dns = Some(started.elapsed());
+
+    let started = Instant::now();
+    let tcp = TcpStream::connect_timeout(&addr, timeout).map_err(|e| anyhow::anyhow!("connect to {} failed: {}", addr, e))?;
+    tcp.set_read_timeout(Some(timeout))?;
+    tcp.set_write_timeout(Some(timeout))?;
+    result.connect = Some(started.elapsed());
+
+    match url.scheme() {
+        "tcp" => Ok(format!("connected to {}", addr)),
+        "http" => http_exchange(tcp, &url, &host, probe.expect_status, result),
+        "smtp" => smtp_banner(tcp, result),
+        "https" | "smtps" => {
+            let started = Instant::now();
+            let tls = tls_connect(tcp, &host).map_err(|e| anyhow::anyhow!("TLS handshake failed: {}", e))?;
+            result.tls = Some(started.elapsed());
+            if url.scheme() == "https" {
+                http_exchange(tls, &url, &host, probe.expect_status, result)
+            } else {
+                smtp_banner(tls, result)
+            }
+        }
+        other => Err(anyhow::anyhow!("unsupported scheme '{}'", other)),
+    }
+}
+
+/// Completes a TLS handshake against the bundled Mozilla root store.
+pub fn tls_connect(mut tcp: TcpStream, host: &str) -> Result<rustls::StreamOwned<rustls::ClientConnection, TcpStream>> {
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(tls::mozilla_roots())
+        .with_no_client_auth();
+    let mut conn = rustls::ClientConnection::new(Arc::new(config), rustls::ServerName::try_from(host)?)?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut tcp)?;
+    }
+    Ok(rustls::StreamOwned::new(conn, tcp))
+}
+
+fn http_exchange(mut stream: impl Read + Write, url: &Url, host: &str, expect: Option<u16>, result: &mut ProbeResult) -> Result<String> {
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: wp-agent\r\nConnection: close\r\n\r\n", path, host);
+
+    let started = Instant::now();
+    stream.write_all(request.as_bytes()).map_err(|e| anyhow::anyhow!("sending request failed: {}", e))?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).map_err(|e| anyhow::anyhow!("reading response failed: {}", e))?;
+    result.response = Some(started.elapsed());
+
+    let status: u16 = line.split_whitespace().nth(1).and_then(|s| s.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("invalid HTTP response: {:?}", line.trim()))?;
+    match expect {
+        Some(expected) if status != expected => Err(anyhow::anyhow!("HTTP {} (expected {})", status, expected)),
+        None if status >= 500 => Err(anyhow::anyhow!("HTTP {}", status)),
+        _ => Ok(format!("HTTP {}", status)),
+    }
+}
+
+fn smtp_banner(stream: impl Read, result: &mut ProbeResult) -> Result<String> {
+    let started = Instant::now();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).map_err(|e| anyhow::anyhow!("reading banner failed: {}", e))?;
+    result.response = Some(started.elapsed());
+
+    if line.starts_with("220") {
+        Ok("SMTP 220".to_string())
+    } else {
+        Err(anyhow::anyhow!("unexpected SMTP banner: {:?}", line.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_http_and_smtp_probes_against_local_servers() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let server = std::thread::spawn(move || -> Result<String> {
+            let (mut stream, _) = listener.accept()?;
+            let mut request = vec![0; 1024];
+            let n = stream.read(&mut request)?;
+            stream.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n")?;
+            let (mut stream, _) = listener.accept()?;
+            stream.write_all(b"220 mail.example.com ESMTP\r\n")?;
+            Ok(String::from_utf8_lossy(&request[..n]).to_string())
+        });
+
+        let http = run(&Probe::new("gateway", &format!("http://{}/v1/ping?x=1", addr), Some(200)));
+        let smtp = run(&Probe::new("relay", &format!("smtp://{}", addr), None));
+        let request = server.join().unwrap()?;
+
+        assert!(request.starts_with("GET /v1/ping?x=1 HTTP/1.1\r\n"));
+        assert_eq!(http.outcome, Err("HTTP 403 (expected 200)".to_string()));
+        assert!(http.dns.is_some() && http.connect.is_some() && http.tls.is_none() && http.response.is_some());
+        assert_eq!(smtp.outcome, Ok("SMTP 220".to_string()));
+        assert!(smtp.timings().starts_with("dns "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_probe_config_defaults() -> Result<()> {
+        let probes: Vec<Probe> = serde_json::from_str(r#"[
+            {"name": "Stripe", "url": "https://api.stripe.com/v1", "expect_status": 401},
+            {"name": "SMTP relay", "url": "smtp://smtp.example.com:587", "timeout_secs": 5}
+        ]"#)?;
+        assert_eq!(probes[0].expect_status, Some(401));
+        assert_eq!(probes[1].timeout_secs, Some(5));
+
+        let refused = run(&Probe::new("closed", "tcp://127.0.0.1:1", None));
+        assert!(refused.outcome.unwrap_err().starts_with("connect to 127.0.0.1:1 failed"));
+        Ok(())
+    }
+}
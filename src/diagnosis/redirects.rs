@@ -0,0 +1,178 @@
+use crate::diagnosis::Status;
+use anyhow::Result;
+use reqwest::header::LOCATION;
+use reqwest::Url;
+
+/// Longer chains are reported as broken, browsers give up around 20.
+pub const MAX_HOPS: usize = 10;
+
+#[derive(Debug, PartialEq)]
+pub struct Hop {
+    pub url: String,
+    pub status: u16,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Ending {
+    /// The last hop answered without redirecting.
+    Final,
+    /// The last hop redirects to a URL already visited.
+    Loop(String),
+    TooLong,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Chain {
+    pub hops: Vec<Hop>,
+    pub ending: Ending,
+}
+
+/// Follows redirects one at a time. `client` must have redirects disabled.
+pub fn trace(client: &reqwest::blocking::Client, start: &str) -> Result<Chain> {
+    let mut hops: Vec<Hop> = Vec::new();
+    let mut url = Url::parse(start)?;
+
+    loop {
+        let response = client.get(url.clone()).send()?;
+        let status = response.status();
+        hops.push(Hop { url: url.to_string(), status: status.as_u16() });
+
+        let location = match response.headers().get(LOCATION).and_then(|l| l.to_str().ok()) {
+            Some(location) if status.is_redirection() => location.to_string(),
+            _ => return Ok(Chain { hops, ending: Ending::Final }),
+        };
+        let next = url.join(&location)?;
+        if hops.iter().any(|h| h.url == next.as_str()) {
+            return Ok(Chain { hops, ending: Ending::Loop(next.to_string()) });
+        }
+        if hops.len() >= MAX_HOPS {
+            return Ok(Chain { hops, ending: Ending::TooLong });
+        }
+        url = next;
+    }
+}
+
+pub fn analyze(home: &str, siteurl: &str, chain: &Chain, status: &mut Status, details: &mut Vec<String>) {
+    if chain.hops.len() > 1 {
+        let path: Vec<String> = chain.hops.iter().map(|h| format!("{} ({})", h.url, h.status)).collect();
+        details.push(format!("Redirect chain: {}", path.join(" -> ")));
+    }
+
+    match &chain.ending {
+        Ending::Loop(url) => {
+            status.escalate(Status::Error);
+            details.push(format!("Critical: Redirect loop back to {}", url));
+        }
+        Ending::TooLong => {
+            status.escalate(Status::Error);
+            details.push(format!("Critical: More than {} redirects from {}", MAX_HOPS, home));
+        }
+        Ending::Final => {
+            let last = chain.hops.last().expect("a chain has at least one hop");
+            if (200..300).contains(&last.status) {
+                details.push(format!("Site reachable: OK ({})", last.status));
+            } else {
+                status.escalate(if last.status >= 500 { Status::Error } else { Status::Warning });
+                details.push(format!("Site returned status: {}", last.status));
+            }
+            if let (Ok(final_url), Ok(home_url)) = (Url::parse(&last.url), Url::parse(home)) {
+                if !same_site(&final_url, &home_url) {
+                    status.escalate(Status::Warning);
+                    details.push(format!("Warning: home is {} but the site ends up at {}{}.", home, last.url,
+                        if is_www_variant(&final_url, &home_url) { " (www/non-www mismatch)" } else { "" }));
+                }
+            }
+        }
+    }
+
+    for pair in chain.hops.windows(2) {
+        if pair[0].url.starts_with("https://") && pair[1].url.starts_with("http://") {
+            status.escalate(Status::Warning);
+            details.push(format!("Warning: Redirect downgrades from https to http: {} -> {}", pair[0].url, pair[1].url));
+        }
+    }
+
+    // WordPress may live in a subdirectory, so only scheme and host have to agree
+    if let (Ok(home_url), Ok(site_url)) = (Url::parse(home), Url::parse(siteurl)) {
+        if home_url.scheme() != site_url.scheme() {
+            status.escalate(Status::Warning);
+            details.push(format!("Warning: home ({}) and siteurl ({}) use different schemes.", home, siteurl));
+        }
+        if home_url.host_str() != site_url.host_str() {
+            status.escalate(Status::Warning);
+            details.push(format!("Warning: home ({}) and siteurl ({}) use different hosts{}.", home, siteurl,
+                if is_www_variant(&home_url, &site_url) { " (www/non-www mismatch)" } else { "" }));
+        }
+    }
+}
+
+fn same_site(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme()
+        && a.host_str() == b.host_str()
+        && a.port_or_known_default() == b.port_or_known_default()
+        && a.path().trim_end_matches('/') == b.path().trim_end_matches('/')
+}
+
+fn is_www_variant(a: &Url, b: &Url) -> bool {
+    let strip = |u: &Url| u.host_str().unwrap_or_default().trim_start_matches("www.").to_string();
+    a.host_str() != b.host_str() && strip(a) == strip(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_trace_detects_loop() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut request_line = String::new();
+                BufReader::new(&stream).read_line(&mut request_line).unwrap();
+                let location = if request_line.starts_with("GET /a ") { "/b".to_string() } else { format!("http://{}/a", addr) };
+                write!(stream, "HTTP/1.1 301 Moved Permanently\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", location).unwrap();
+            }
+        });
+
+        let client = reqwest::blocking::Client::builder().redirect(reqwest::redirect::Policy::none()).build()?;
+        let home = format!("http://{}/a", addr);
+        let chain = trace(&client, &home)?;
+
+        assert_eq!(chain.hops.len(), 2);
+        assert_eq!(chain.hops[1].url, format!("http://{}/b", addr));
+        assert_eq!(chain.ending, Ending::Loop(home.clone()));
+
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        analyze(&home, &home, &chain, &mut status, &mut details);
+        assert_eq!(status, Status::Error);
+        assert!(details.iter().any(|d| d.starts_with("Critical: Redirect loop back to")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_www_mismatch_and_downgrade() {
+        let chain = Chain {
+            hops: vec![
+                Hop { url: "https://example.com/".to_string(), status: 301 },
+                Hop { url: "http://www.example.com/".to_string(), status: 301 },
+                Hop { url: "https://www.example.com/".to_string(), status: 200 },
+            ],
+            ending: Ending::Final,
+        };
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+
+        analyze("https://example.com", "http://example.com/wp", &chain, &mut status, &mut details);
+
+        assert_eq!(status, Status::Warning);
+        assert!(details.iter().any(|d| d == "Site reachable: OK (200)"));
+        assert!(details.iter().any(|d| d.ends_with("ends up at https://www.example.com/ (www/non-www mismatch).")));
+        assert!(details.iter().any(|d| d.starts_with("Warning: Redirect downgrades from https to http")));
+        assert!(details.iter().any(|d| d.contains("use different schemes")));
+    }
+}
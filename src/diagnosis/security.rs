@@ -1,4 +1,8 @@
-use crate::diagnosis::{Diagnosis, DiagnosisReport, Status};
+use crate::diagnosis::{Diagnosis, DiagnosisReport, SiteUrls, Status};
+use crate::diagnosis::checksums::{self, FileFinding, FileIssue, PluginChecksums};
+use crate::diagnosis::{exposure, injection, malware, permissions};
+use crate::fix::Fix;
+use crate::fix::actions::{NormalizePermissions, SetConfigConstant};
 use crate::wp::WpCli;
 use std::path::Path;
 use anyhow::Result;
@@ -13,22 +17,43 @@ impl Diagnosis for SecurityDiagnosis {
 
         // 1. Core Verify Checksums
         println!("    > Verifying core checksums...");
-        match wp.run(&["core", "verify-checksums"], root) {
-            Ok(_) => {
+        match wp.run_unchecked(&["core", "verify-checksums"], root) {
+            Ok(output) if output.status.success() => {
                 details.push("Core checksums verified.".to_string());
             }
-            Err(_) => {
-                overall_status = Status::Warning;
-                details.push("Warning: Core checksums verification failed. Core files may be modified.".to_string());
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let findings = checksums::parse_core_checksums(&stderr);
+                if findings.is_empty() {
+                    overall_status.escalate(Status::Warning);
+                    details.push(format!("Warning: Could not verify core checksums: {}", stderr.trim()));
+                } else {
+                    self.analyze_core_checksums(&findings, &mut overall_status, &mut details);
+                }
             }
+            Err(e) => details.push(format!("Could not verify core checksums: {}", e)),
         }
 
-        // 2. Check Debug Mode
+        // 2. Plugin Verify Checksums
+        println!("    > Verifying plugin checksums...");
+        match wp.run_unchecked(&["plugin", "verify-checksums", "--all", "--format=json"], root) {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                match checksums::parse_plugin_checksums(&stdout, &stderr) {
+                    Ok(result) => self.analyze_plugin_checksums(&result, &mut overall_status, &mut details),
+                    Err(e) => details.push(format!("Could not verify plugin checksums: {}", e)),
+                }
+            }
+            Err(e) => details.push(format!("Could not verify plugin checksums: {}", e)),
+        }
+
+        // 3. Check Debug Mode
         println!("    > Checking WP_DEBUG status...");
         match wp.run(&["config", "get", "WP_DEBUG"], root) {
             Ok(output) => {
-                if output.trim() == "true" || output.trim() == "1" {
-                     overall_status = Status::Warning;
+                if is_enabled(&output) {
+                     overall_status.escalate(Status::Warning);
                      details.push("Warning: WP_DEBUG is enabled.".to_string());
                 } else {
                      details.push("WP_DEBUG is disabled.".to_string());
@@ -39,7 +64,7 @@ impl Diagnosis for SecurityDiagnosis {
             }
         }
 
-        // 3. User Audit (Admin check)
+        // 4. User Audit (Admin check)
         println!("    > Checking for admin user...");
         match wp.run(&["user", "list", "--role=administrator", "--field=user_login", "--format=csv"], root) {
              Ok(output) => {
@@ -50,6 +75,33 @@ impl Diagnosis for SecurityDiagnosis {
              }
         }
 
+        // 5. Sensitive files (backups, dumps, logs, VCS metadata) and whether the web server hands them out
+        println!("    > Checking for exposed sensitive files...");
+        let urls = SiteUrls::fetch(wp, root).ok();
+        exposure::check(root, urls.as_ref().map(|u| u.siteurl.as_str()), &mut overall_status, &mut details);
+
+        // 6. File and directory permissions
+        println!("    > Reviewing file permissions...");
+        let findings = permissions::scan(root, WpCli::site_owner(root));
+        permissions::analyze(&findings, &mut overall_status, &mut details);
+
+        // 7. PHP in uploads and webshell signatures
+        println!("    > Scanning wp-content for webshells...");
+        match malware::load_rules() {
+            Ok(rules) => {
+                let scan = malware::scan(&root.join("wp-content"), &rules, std::time::SystemTime::now());
+                malware::analyze(&scan, &mut overall_status, &mut details);
+            }
+            Err(e) => {
+                overall_status.escalate(Status::Warning);
+                details.push(format!("Warning: {}", e));
+            }
+        }
+
+        // 8. Scripts, spam links and payloads injected into the database
+        println!("    > Scanning the database for injected content...");
+        injection::check(wp, root, &mut overall_status, &mut details);
+
         Ok(DiagnosisReport {
             module: "Security".to_string(),
             status: overall_status,
@@ -57,9 +109,83 @@ impl Diagnosis for SecurityDiagnosis {
             details,
         })
     }
+
+    fn fixes(&self, wp: &WpCli, root: &Path) -> Result<Vec<Box<dyn Fix>>> {
+        let mut fixes: Vec<Box<dyn Fix>> = Vec::new();
+
+        if wp.run(&["config", "get", "WP_DEBUG"], root).is_ok_and(|o| is_enabled(&o)) {
+            fixes.push(Box::new(SetConfigConstant {
+                name: "WP_DEBUG".to_string(),
+                value: "false".to_string(),
+            }));
+        }
+
+        let mut paths: Vec<String> = permissions::scan(root, None).into_iter()
+            .filter(|f| f.issue.fixable())
+            .map(|f| f.path)
+            .collect();
+        paths.dedup();
+        if !paths.is_empty() {
+            fixes.push(Box::new(NormalizePermissions { paths }));
+        }
+
+        Ok(fixes)
+    }
+}
+
+fn is_enabled(config_value: &str) -> bool {
+    config_value.trim() == "true" || config_value.trim() == "1"
 }
 
 impl SecurityDiagnosis {
+    fn analyze_core_checksums(&self, findings: &[FileFinding], status: &mut Status, details: &mut Vec<String>) {
+        details.push(format!("Core checksums verification failed for {} files:", findings.len()));
+
+        for finding in findings {
+            if checksums::is_high_severity(finding) {
+                status.escalate(Status::Error);
+                details.push(format!("Critical: Unexpected PHP file in core directory: {}", finding.file));
+                continue;
+            }
+
+            status.escalate(Status::Warning);
+            let line = match finding.issue {
+                FileIssue::Modified => "Core file modified",
+                FileIssue::Added => "Unexpected file in core",
+                FileIssue::Missing => "Core file missing",
+                FileIssue::Other(_) => "Core file problem",
+            };
+            details.push(format!("Warning: {}: {}", line, finding.file));
+        }
+
+        if findings.iter().any(|f| f.issue == FileIssue::Modified) {
+            details.push("Run `wp-agent core-diff` to see what changed in the modified files.".to_string());
+        }
+    }
+
+    fn analyze_plugin_checksums(&self, result: &PluginChecksums, status: &mut Status, details: &mut Vec<String>) {
+        if result.failed.is_empty() {
+            details.push("Plugin checksums verified.".to_string());
+        }
+
+        for (plugin, findings) in &result.failed {
+            status.escalate(Status::Warning);
+            let count = |issue: FileIssue| findings.iter().filter(|f| f.issue == issue).count();
+            details.push(format!("Warning: Plugin '{}' failed checksum verification ({} modified, {} added, {} missing):",
+                plugin, count(FileIssue::Modified), count(FileIssue::Added), count(FileIssue::Missing)));
+            for finding in findings.iter().take(10) {
+                details.push(format!("   {}: {}", finding.issue.label(), finding.file));
+            }
+            if findings.len() > 10 {
+                details.push(format!("   ... and {} more files", findings.len() - 10));
+            }
+        }
+
+        for plugin in &result.unverifiable {
+            details.push(format!("Note: Plugin '{}' could not be verified (not hosted on wordpress.org, or unknown version).", plugin));
+        }
+    }
+
     fn analyze_users(&self, csv_output: &str, status: &mut Status, details: &mut Vec<String>) {
         let admins: Vec<&str> = csv_output.lines()
             .map(|l| l.trim())
@@ -69,7 +195,7 @@ impl SecurityDiagnosis {
         details.push(format!("Found {} administrator(s).", admin_count));
 
         if admins.contains(&"admin") {
-            *status = Status::Warning;
+            status.escalate(Status::Warning);
             details.push("Warning: Insecure username 'admin' exists.".to_string());
         }
         
@@ -97,6 +223,41 @@ mod tests {
         assert!(details.iter().any(|d| d.contains("Found 2 administrator(s)")));
     }
 
+    #[test]
+    fn test_analyze_core_checksums_flags_php_in_core_dirs() {
+        let diagnosis = SecurityDiagnosis;
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        let findings = checksums::parse_core_checksums(
+            "Warning: File doesn't verify against checksum: wp-includes/version.php\n\
+             Warning: File should not exist: wp-includes/images/x.php",
+        );
+
+        diagnosis.analyze_core_checksums(&findings, &mut status, &mut details);
+
+        assert_eq!(status, Status::Error);
+        assert!(details.iter().any(|d| d == "Warning: Core file modified: wp-includes/version.php"));
+        assert!(details.iter().any(|d| d == "Critical: Unexpected PHP file in core directory: wp-includes/images/x.php"));
+    }
+
+    #[test]
+    fn test_analyze_plugin_checksums() -> Result<()> {
+        let diagnosis = SecurityDiagnosis;
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+        let result = checksums::parse_plugin_checksums(
+            r#"[{"plugin_name":"akismet","file":"shell.php","message":"File was added"}]"#,
+            "Warning: Could not retrieve the checksums for version 2.1 of plugin premium-forms, skipping.",
+        )?;
+
+        diagnosis.analyze_plugin_checksums(&result, &mut status, &mut details);
+
+        assert_eq!(status, Status::Warning);
+        assert!(details.iter().any(|d| d.contains("'akismet' failed checksum verification (0 modified, 1 added, 0 missing)")));
+        assert!(details.iter().any(|d| d.contains("'premium-forms' could not be verified")));
+        Ok(())
+    }
+
     #[test]
     fn test_analyze_users_safe() {
         let diagnosis = SecurityDiagnosis;
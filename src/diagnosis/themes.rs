@@ -0,0 +1,292 @@
+use crate::core_diff;
+use crate::diagnosis::{self, Diagnosis, DiagnosisReport, Status};
+use crate::diagnosis::checksums::{FileFinding, FileIssue};
+use crate::fsutil;
+use crate::wp::WpCli;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Verification findings listed per theme.
+const MAX_LISTED: usize = 10;
+
+#[derive(Deserialize, Debug)]
+struct Theme {
+    name: String,
+    status: String, // "active", "parent" or "inactive"
+    update: String, // "available" or "none"
+    version: String,
+}
+
+pub struct ThemeDiagnosis;
+
+impl Diagnosis for ThemeDiagnosis {
+    fn run(&self, wp: &WpCli, root: &Path) -> Result<DiagnosisReport> {
+        println!("  Running Theme Diagnosis...");
+
+        println!("    > Fetching theme list...");
+        let themes: Vec<Theme> = serde_json::from_str(&wp.run(&["theme", "list", "--format=json"], root)?)?;
+
+        // The list does not say which theme is the parent of the active one
+        let template = match themes.iter().find(|t| t.status == "active") {
+            Some(active) => wp.run(&["theme", "get", &active.name, "--field=template"], root)
+                .ok()
+                .map(|t| t.trim().to_string()),
+            None => None,
+        };
+
+        let mut report = self.analyze_themes(&themes, template.as_deref());
+
+        println!("    > Verifying bundled theme files...");
+        self.verify_bundled(wp, root, &themes, &mut report);
+
+        Ok(report)
+    }
+}
+
+impl ThemeDiagnosis {
+    fn analyze_themes(&self, themes: &[Theme], active_template: Option<&str>) -> DiagnosisReport {
+        let mut details = Vec::new();
+        let mut overall_status = Status::Ok;
+
+        // Active theme and its parent
+        match themes.iter().find(|t| t.status == "active") {
+            Some(active) => {
+                details.push(format!("Active theme: {} ({})", active.name, active.version));
+                if let Some(template) = active_template.filter(|t| *t != active.name) {
+                    match themes.iter().find(|t| t.name == template) {
+                        Some(parent) => details.push(format!("Child theme of: {} ({})", parent.name, parent.version)),
+                        None => {
+                            overall_status.escalate(Status::Error);
+                            details.push(format!("Error: Parent theme '{}' of the active theme is missing.", template));
+                        }
+                    }
+                }
+            }
+            None => {
+                overall_status.escalate(Status::Error);
+                details.push("Error: No active theme found.".to_string());
+            }
+        }
+
+        // Check for updates
+        let updates_available: Vec<&Theme> = themes.iter().filter(|t| t.update == "available").collect();
+        if !updates_available.is_empty() {
+             overall_status.escalate(Status::Warning);
+             details.push(format!("{} themes have updates available:", updates_available.len()));
+             for t in updates_available {
+                 details.push(format!(" - {} ({})", t.name, t.version));
+             }
+        } else {
+             details.push("All themes are up to date.".to_string());
+        }
+
+        // Inactive themes are still reachable over HTTP, so they add attack surface
+        let inactive: Vec<&Theme> = themes.iter().filter(|t| t.status == "inactive").collect();
+        if !inactive.is_empty() {
+             details.push(format!("{} unused inactive themes found (consider removing them, keep one default theme as fallback):", inactive.len()));
+             for t in inactive {
+                  details.push(format!(" - {}", t.name));
+             }
+        }
+
+        DiagnosisReport {
+            module: "Themes".to_string(),
+            status: overall_status,
+            message: format!("Analyzed {} themes.", themes.len()),
+            details,
+        }
+    }
+
+    /// Default themes (twenty*) ship inside the core release, so they can be verified offline
+    /// against WP-CLI's cached core archive when the versions match.
+    fn verify_bundled(&self, wp: &WpCli, root: &Path, themes: &[Theme], report: &mut DiagnosisReport) {
+        let bundled: Vec<&Theme> = themes.iter().filter(|t| t.name.starts_with("twenty")).collect();
+        if bundled.is_empty() {
+            return;
+        }
+
+        let archive = match wp.run(&["core", "version"], root).and_then(|v| core_diff::find_cached_archive(v.trim())) {
+            Ok(archive) => archive,
+            Err(_) => {
+                report.details.push("Note: Theme files not verified (no cached core archive).".to_string());
+                return;
+            }
+        };
+
+        let slugs: Vec<&str> = bundled.iter().map(|t| t.name.as_str()).collect();
+        let mut pristine = match read_bundled(&archive, &slugs) {
+            Ok(pristine) => pristine,
+            Err(e) => {
+                report.details.push(format!("Could not read core archive {:?}: {}", archive, e));
+                return;
+            }
+        };
+
+        for theme in bundled {
+            let dir = root.join("wp-content/themes").join(&theme.name);
+            let result = match pristine.remove(&theme.name) {
+                Some(copy) => verify_against(copy, &theme.version, &dir),
+                None => Ok(None),
+            };
+            match result {
+                Ok(Some(findings)) if findings.is_empty() => {
+                    report.details.push(format!("Theme '{}' files verified.", theme.name));
+                }
+                Ok(Some(findings)) => {
+                    report.status.escalate(Status::Warning);
+                    report.details.push(format!("Warning: Theme '{}' failed verification:", theme.name));
+                    for finding in findings.iter().take(MAX_LISTED) {
+                        report.details.push(format!("   {}: {}", finding.issue.label(), finding.file));
+                    }
+                    if findings.len() > MAX_LISTED {
+                        report.details.push(format!("   ... and {} more", findings.len() - MAX_LISTED));
+                    }
+                }
+                Ok(None) => report.details.push(format!(
+                    "Note: Theme '{}' {} is not the version bundled with this core release, not verified.", theme.name, theme.version)),
+                Err(e) => report.details.push(format!("Could not verify theme '{}': {}", theme.name, e)),
+            }
+        }
+    }
+}
+
+/// A theme's files as shipped in a core archive, keyed by path relative to the theme.
+#[derive(Default)]
+struct PristineTheme {
+    version: Option<String>,
+    hashes: BTreeMap<String, String>,
+}
+
+/// Reads the copies of `slugs` from a core archive in a single pass.
+fn read_bundled(archive: &Path, slugs: &[&str]) -> Result<HashMap<String, PristineTheme>> {
+    let themes_dir = Path::new("wordpress/wp-content/themes");
+    let mut bundled: HashMap<String, PristineTheme> = HashMap::new();
+
+    let mut tar = tar::Archive::new(GzDecoder::new(fs::File::open(archive)?));
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        let mut components = match path.strip_prefix(themes_dir) {
+            Ok(rel) => rel.components(),
+            Err(_) => continue,
+        };
+        let slug = match components.next().and_then(|c| c.as_os_str().to_str()) {
+            Some(slug) if slugs.contains(&slug) => slug.to_string(),
+            _ => continue,
+        };
+        let rel = components.as_path().to_string_lossy().to_string();
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+
+        let theme = bundled.entry(slug).or_default();
+        if rel == "style.css" {
+            theme.version = diagnosis::file_header(&String::from_utf8_lossy(&content), "Version");
+        }
+        theme.hashes.insert(rel, format!("{:x}", Sha256::digest(&content)));
+    }
+    Ok(bundled)
+}
+
+/// Compares a theme directory with its pristine copy.
+/// Returns `None` when the archive holds a different version of the theme.
+fn verify_against(pristine: PristineTheme, version: &str, dir: &Path) -> Result<Option<Vec<FileFinding>>> {
+    let PristineTheme { version: pristine_version, hashes: mut pristine } = pristine;
+    if pristine_version.as_deref() != Some(version) {
+        return Ok(None);
+    }
+
+    let mut findings = Vec::new();
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(dir)?.to_string_lossy().to_string();
+        match pristine.remove(&rel) {
+            Some(hash) if hash != fsutil::sha256_file(entry.path())? => {
+                findings.push(FileFinding { issue: FileIssue::Modified, file: rel });
+            }
+            Some(_) => {}
+            None => findings.push(FileFinding { issue: FileIssue::Added, file: rel }),
+        }
+    }
+    findings.extend(pristine.into_keys().map(|file| FileFinding { issue: FileIssue::Missing, file }));
+    Ok(Some(findings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_themes_child_and_updates() -> Result<()> {
+        let json = r#"[
+            {"name": "astra-child", "status": "active", "update": "none", "version": "1.0"},
+            {"name": "astra", "status": "parent", "update": "available", "version": "4.0"},
+            {"name": "twentytwenty", "status": "inactive", "update": "none", "version": "2.1"}
+        ]"#;
+
+        let report = ThemeDiagnosis.analyze_themes(&serde_json::from_str::<Vec<Theme>>(json)?, Some("astra"));
+
+        assert_eq!(report.module, "Themes");
+        assert_eq!(report.status, Status::Warning);
+        assert!(report.details.iter().any(|d| d.contains("Child theme of: astra (4.0)")));
+        assert!(report.details.iter().any(|d| d.contains("1 themes have updates available")));
+        assert!(report.details.iter().any(|d| d.contains("1 unused inactive themes found")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_themes_missing_parent() -> Result<()> {
+        let json = r#"[{"name": "astra-child", "status": "active", "update": "none", "version": "1.0"}]"#;
+
+        let report = ThemeDiagnosis.analyze_themes(&serde_json::from_str::<Vec<Theme>>(json)?, Some("astra"));
+
+        assert_eq!(report.status, Status::Error);
+        assert!(report.details.iter().any(|d| d.contains("Parent theme 'astra' of the active theme is missing")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_bundled_theme() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join("wordpress.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            fs::File::create(&archive)?, flate2::Compression::default()));
+        for (name, content) in [
+            ("wordpress/wp-content/themes/twentytwenty/style.css", "/*\nTheme Name: Twenty Twenty\nVersion: 2.1\n*/"),
+            ("wordpress/wp-content/themes/twentytwenty/index.php", "<?php // index"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, content.as_bytes())?;
+        }
+        builder.into_inner()?.finish()?;
+
+        let theme = dir.path().join("twentytwenty");
+        fs::create_dir_all(&theme)?;
+        fs::write(theme.join("style.css"), "/*\nTheme Name: Twenty Twenty\nVersion: 2.1\n*/")?;
+        fs::write(theme.join("index.php"), "<?php eval($_POST['x']);")?;
+
+        let mut bundled = read_bundled(&archive, &["twentytwenty", "twentytwentyone"])?;
+        assert_eq!(bundled.len(), 1);
+        let copy = bundled.remove("twentytwenty").unwrap();
+        assert_eq!(copy.version.as_deref(), Some("2.1"));
+
+        let findings = verify_against(copy, "2.1", &theme)?.unwrap();
+        assert_eq!(findings, vec![FileFinding { issue: FileIssue::Modified, file: "index.php".to_string() }]);
+        let copy = read_bundled(&archive, &["twentytwenty"])?.remove("twentytwenty").unwrap();
+        assert!(verify_against(copy, "2.2", &theme)?.is_none());
+        Ok(())
+    }
+}
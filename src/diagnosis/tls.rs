@@ -0,0 +1,350 @@
+use crate::diagnosis::Status;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, CertificateError, RootCertStore, ServerName};
+
+/// Certificates expiring within this many days are a warning, within `EXPIRY_CRITICAL_DAYS` an error.
+const EXPIRY_WARNING_DAYS: i64 = 30;
+const EXPIRY_CRITICAL_DAYS: i64 = 7;
+
+/// Protocol versions that should no longer be accepted, with their ClientHello version bytes.
+const LEGACY_PROTOCOLS: &[(&str, u16)] = &[("TLS 1.0", 0x0301), ("TLS 1.1", 0x0302)];
+
+#[derive(Debug, PartialEq)]
+pub struct CertInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub not_after: DateTime<Utc>,
+}
+
+/// Outcome of verifying the served chain like a browser would.
+#[derive(Debug, PartialEq)]
+pub enum Verification {
+    Valid,
+    Expired,
+    NameMismatch,
+    SelfSigned,
+    /// The issuer is unknown and the server did not send it, usually a missing intermediate.
+    IncompleteChain,
+    Other(String),
+}
+
+#[derive(Debug)]
+pub struct TlsReport {
+    pub protocol: String,
+    pub chain: Vec<CertInfo>,
+    pub verification: Verification,
+    /// Legacy protocol versions the server still accepts.
+    pub legacy: Vec<&'static str>,
+}
+
+/// The Mozilla root store bundled with webpki-roots.
+pub fn mozilla_roots() -> RootCertStore {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(ta.subject, ta.spki, ta.name_constraints)
+    }));
+    roots
+}
+
+/// Accepts any certificate and keeps the chain, so it can be inspected even when it is broken.
+struct CaptureChain(Mutex<Vec<Certificate>>);
+
+impl ServerCertVerifier for CaptureChain {
+    fn verify_server_cert(&self, end_entity: &Certificate, intermediates: &[Certificate], _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>, _ocsp_response: &[u8], _now: SystemTime) -> Result<ServerCertVerified, rustls::Error> {
+        let mut chain = self.0.lock().unwrap();
+        chain.push(end_entity.clone());
+        chain.extend(intermediates.iter().cloned());
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Connects to `addr`, presenting `host` for SNI, and checks the certificate chain against `roots`.
+pub fn inspect(addr: SocketAddr, host: &str, roots: RootCertStore, timeout: Duration) -> Result<TlsReport> {
+    let capture = Arc::new(CaptureChain(Mutex::new(Vec::new())));
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(capture.clone())
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(host)?;
+
+    let mut tcp = TcpStream::connect_timeout(&addr, timeout)?;
+    tcp.set_read_timeout(Some(timeout))?;
+    tcp.set_write_timeout(Some(timeout))?;
+    let mut conn = rustls::ClientConnection::new(Arc::new(config), server_name.clone())?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut tcp)?;
+    }
+    let protocol = match conn.protocol_version() {
+        Some(rustls::ProtocolVersion::TLSv1_3) => "TLS 1.3".to_string(),
+        Some(rustls::ProtocolVersion::TLSv1_2) => "TLS 1.2".to_string(),
+        other => format!("{:?}", other),
+    };
+
+    let certs = capture.0.lock().unwrap().clone();
+    let chain: Vec<CertInfo> = certs.iter().filter_map(|c| describe(&c.0)).collect();
+    let leaf = certs.first().ok_or_else(|| anyhow::anyhow!("server sent no certificate"))?;
+    let verifier = WebPkiVerifier::new(roots, None);
+    let verification = match verifier.verify_server_cert(leaf, &certs[1..], &server_name, &mut std::iter::empty(), &[], SystemTime::now()) {
+        Ok(_) => Verification::Valid,
+        Err(rustls::Error::InvalidCertificate(CertificateError::Expired)) => Verification::Expired,
+        Err(rustls::Error::InvalidCertificate(CertificateError::NotValidForName)) => Verification::NameMismatch,
+        Err(rustls::Error::InvalidCertificate(CertificateError::UnknownIssuer)) => {
+            match chain.first() {
+                Some(cert) if cert.subject == cert.issuer => Verification::SelfSigned,
+                _ => Verification::IncompleteChain,
+            }
+        }
+        Err(e) => Verification::Other(e.to_string()),
+    };
+
+    let legacy = LEGACY_PROTOCOLS.iter()
+        .filter(|(_, version)| accepts_legacy(addr, host, *version, timeout))
+        .map(|(name, _)| *name)
+        .collect();
+
+    Ok(TlsReport { protocol, chain, verification, legacy })
+}
+
+fn describe(der: &[u8]) -> Option<CertInfo> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+    Some(CertInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        not_after: DateTime::from_timestamp(cert.validity().not_after.timestamp(), 0)?,
+    })
+}
+
+/// rustls cannot speak TLS 1.0/1.1, so send a minimal ClientHello by hand
+/// and see whether the server answers with a ServerHello of that version.
+fn accepts_legacy(addr: SocketAddr, host: &str, version: u16, timeout: Duration) -> bool {
+    let exchange = || -> Result<bool> {
+        let mut tcp = TcpStream::connect_timeout(&addr, timeout)?;
+        tcp.set_read_timeout(Some(timeout))?;
+        tcp.write_all(&client_hello(host, version))?;
+
+        let mut header = [0u8; 5];
+        tcp.read_exact(&mut header)?;
+        if header[0] != 0x16 {
+            return Ok(false); // alert
+        }
+        let mut body = [0u8; 6];
+        tcp.read_exact(&mut body)?;
+        Ok(body[0] == 0x02 && u16::from_be_bytes([body[4], body[5]]) == version)
+    };
+    exchange().unwrap_or(false)
+}
+
+fn client_hello(host: &str, version: u16) -> Vec<u8> {
+    let with_len16 = |data: &[u8]| [&(data.len() as u16).to_be_bytes()[..], data].concat();
+
+    let sni = with_len16(&[&[0u8][..], &with_len16(host.as_bytes())].concat());
+    let extensions = [
+        &[0x00, 0x00][..], &with_len16(&sni),                                 // server_name
+        &[0x00, 0x0a], &with_len16(&with_len16(&[0x00, 0x17, 0x00, 0x18])),   // supported_groups
+        &[0x00, 0x0b], &with_len16(&[0x01, 0x00]),                            // ec_point_formats
+        &[0xff, 0x01], &with_len16(&[0x00]),                                  // renegotiation_info
+    ].concat();
+    // ECDHE and RSA key exchange with AES-CBC and 3DES, as offered by old clients
+    let suites = [0xc0, 0x13, 0xc0, 0x14, 0xc0, 0x09, 0xc0, 0x0a, 0x00, 0x2f, 0x00, 0x35, 0x00, 0x0a];
+
+    let hello = [
+        &version.to_be_bytes()[..], &[0x42; 32], &[0x00],
+        &with_len16(&suites), &[0x01, 0x00], &with_len16(&extensions),
+    ].concat();
+    let handshake = [&[0x01][..], &(hello.len() as u32).to_be_bytes()[1..], &hello].concat();
+    [&[0x16, 0x03, 0x01][..], &with_len16(&handshake)].concat()
+}
+
+pub fn analyze(report: &TlsReport, now: DateTime<Utc>, status: &mut Status, details: &mut Vec<String>) {
+    details.push(format!("TLS: {} negotiated", report.protocol));
+
+    match &report.verification {
+        Verification::Valid => details.push("TLS certificate chain is valid.".to_string()),
+        Verification::Expired => {
+            status.escalate(Status::Error);
+            details.push("Critical: TLS certificate has expired.".to_string());
+        }
+        Verification::NameMismatch => {
+            status.escalate(Status::Error);
+            details.push("Critical: TLS certificate is not valid for the site hostname.".to_string());
+        }
+        Verification::SelfSigned => {
+            status.escalate(Status::Error);
+            details.push("Critical: TLS certificate is self-signed.".to_string());
+        }
+        Verification::IncompleteChain => {
+            status.escalate(Status::Warning);
+            details.push("Warning: TLS certificate chain is incomplete (intermediate certificate missing), some clients will reject it.".to_string());
+        }
+        Verification::Other(e) => {
+            status.escalate(Status::Error);
+            details.push(format!("Critical: TLS certificate is not trusted: {}", e));
+        }
+    }
+
+    if let Some(leaf) = report.chain.first() {
+        let days = (leaf.not_after - now).num_days();
+        details.push(format!("Certificate: {} issued by {}, expires {} ({} days)",
+            leaf.subject, leaf.issuer, leaf.not_after.format("%Y-%m-%d"), days));
+        if (0..EXPIRY_CRITICAL_DAYS).contains(&days) {
+            status.escalate(Status::Error);
+            details.push(format!("Critical: TLS certificate expires in {} days.", days));
+        } else if (0..EXPIRY_WARNING_DAYS).contains(&days) {
+            status.escalate(Status::Warning);
+            details.push(format!("Warning: TLS certificate expires in {} days.", days));
+        }
+    }
+
+    if !report.legacy.is_empty() {
+        status.escalate(Status::Warning);
+        details.push(format!("Warning: Server still accepts {}.", report.legacy.join(" and ")));
+    }
+}
+
+/// Checks that the site is configured for https: both URLs, the http redirect and HSTS.
+pub fn analyze_https(home: &str, siteurl: &str, http_redirect: Option<&str>, hsts: Option<&str>,
+    status: &mut Status, details: &mut Vec<String>) {
+    for (name, url) in [("home", home), ("siteurl", siteurl)] {
+        if !url.starts_with("https://") {
+            status.escalate(Status::Warning);
+            details.push(format!("Warning: The {} URL does not use https: {}", name, url));
+        }
+    }
+    if !home.starts_with("https://") {
+        return;
+    }
+
+    match http_redirect {
+        Some(location) if location.starts_with("https://") => details.push("http:// requests redirect to https://.".to_string()),
+        Some(location) => {
+            status.escalate(Status::Warning);
+            details.push(format!("Warning: http:// requests redirect to {} instead of https.", location));
+        }
+        None => {
+            status.escalate(Status::Warning);
+            details.push("Warning: http:// requests are not redirected to https://.".to_string());
+        }
+    }
+
+    match hsts {
+        Some(value) => details.push(format!("HSTS: {}", value)),
+        None => details.push("Note: No Strict-Transport-Security (HSTS) header.".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, DistinguishedName, DnType, IsCa};
+    use std::net::TcpListener;
+
+    /// Serves `chain` (PEM-less DER) for a single connection.
+    fn serve(chain: Vec<Vec<u8>>, key: Vec<u8>) -> Result<SocketAddr> {
+        let config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(chain.into_iter().map(Certificate).collect(), rustls::PrivateKey(key))?;
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        std::thread::spawn(move || {
+            // One verified handshake plus one attempt per legacy protocol
+            for stream in listener.incoming().take(1 + LEGACY_PROTOCOLS.len()) {
+                let Ok(mut tcp) = stream else { continue };
+                let mut conn = rustls::ServerConnection::new(Arc::new(config.clone())).unwrap();
+                while conn.is_handshaking() {
+                    if conn.complete_io(&mut tcp).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(addr)
+    }
+
+    fn certificate(name: &str, is_ca: bool) -> Result<rcgen::Certificate> {
+        let mut params = CertificateParams::new(if is_ca { vec![] } else { vec![name.to_string()] });
+        params.distinguished_name = DistinguishedName::new();
+        params.distinguished_name.push(DnType::CommonName, name);
+        if is_ca {
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        }
+        Ok(rcgen::Certificate::from_params(params)?)
+    }
+
+    #[test]
+    fn test_inspect_local_tls_server() -> Result<()> {
+        let ca = certificate("Test Root CA", true)?;
+        let intermediate = certificate("Test Intermediate", true)?;
+        let leaf = certificate("localhost", false)?;
+
+        let leaf_der = leaf.serialize_der_with_signer(&intermediate)?;
+        let intermediate_der = intermediate.serialize_der_with_signer(&ca)?;
+        let mut roots = RootCertStore::empty();
+        roots.add(&Certificate(ca.serialize_der()?))?;
+        let timeout = Duration::from_secs(5);
+
+        let addr = serve(vec![leaf_der.clone(), intermediate_der.clone()], leaf.serialize_private_key_der())?;
+        let report = inspect(addr, "localhost", roots.clone(), timeout)?;
+        assert_eq!(report.verification, Verification::Valid);
+        assert_eq!(report.protocol, "TLS 1.3");
+        assert_eq!(report.chain.len(), 2);
+        assert!(report.legacy.is_empty());
+
+        let addr = serve(vec![leaf_der.clone()], leaf.serialize_private_key_der())?;
+        assert_eq!(inspect(addr, "localhost", roots.clone(), timeout)?.verification, Verification::IncompleteChain);
+
+        let addr = serve(vec![leaf_der, intermediate_der], leaf.serialize_private_key_der())?;
+        assert_eq!(inspect(addr, "example.com", roots.clone(), timeout)?.verification, Verification::NameMismatch);
+
+        let self_signed = certificate("localhost", false)?;
+        let addr = serve(vec![self_signed.serialize_der()?], self_signed.serialize_private_key_der())?;
+        assert_eq!(inspect(addr, "localhost", roots, timeout)?.verification, Verification::SelfSigned);
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_expiry_and_legacy_protocols() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let report = TlsReport {
+            protocol: "TLS 1.2".to_string(),
+            chain: vec![CertInfo {
+                subject: "CN=example.com".to_string(),
+                issuer: "CN=R3, O=Let's Encrypt".to_string(),
+                not_after: now + chrono::Duration::days(12),
+            }],
+            verification: Verification::Valid,
+            legacy: vec!["TLS 1.0"],
+        };
+        let mut status = Status::Ok;
+        let mut details = Vec::new();
+
+        analyze(&report, now, &mut status, &mut details);
+
+        assert_eq!(status, Status::Warning);
+        assert!(details.iter().any(|d| d == "Warning: TLS certificate expires in 12 days."));
+        assert!(details.iter().any(|d| d == "Warning: Server still accepts TLS 1.0."));
+    }
+
+    #[test]
+    fn test_accepts_legacy_server_hello() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut hello = [0u8; 5];
+            stream.read_exact(&mut hello).unwrap();
+            stream.write_all(&[0x16, 0x03, 0x01, 0x00, 0x06, 0x02, 0x00, 0x00, 0x02, 0x03, 0x01]).unwrap();
+        });
+
+        assert!(accepts_legacy(addr, "localhost", 0x0301, Duration::from_secs(5)));
+        assert_eq!(&client_hello("localhost", 0x0302)[..3], &[0x16, 0x03, 0x01]);
+        Ok(())
+    }
+}
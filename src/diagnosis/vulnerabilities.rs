@@ -0,0 +1,160 @@
+use crate::diagnosis::{Diagnosis, DiagnosisReport, Status};
+use crate::fix::Fix;
+use crate::fix::actions::DeactivatePlugin;
+use crate::vulndb::{SoftwareType, VulnDb, Vulnerability};
+use crate::wp::WpCli;
+use std::path::Path;
+use anyhow::Result;
+use serde::Deserialize;
+
+/// An installed plugin, theme or core, as returned by `wp plugin/theme list`.
+#[derive(Deserialize, Debug)]
+struct Installed {
+    name: String,
+    status: String,
+    version: String,
+}
+
+pub struct VulnerabilityDiagnosis;
+
+impl Diagnosis for VulnerabilityDiagnosis {
+    fn run(&self, wp: &WpCli, root: &Path) -> Result<DiagnosisReport> {
+        println!("  Running Vulnerability Diagnosis...");
+
+        let db = match VulnDb::load()? {
+            Some(db) => db,
+            None => {
+                return Ok(DiagnosisReport {
+                    module: "Vulnerabilities".to_string(),
+                    status: Status::Ok,
+                    message: "No vulnerability database imported.".to_string(),
+                    details: vec!["Run `wp-agent vulndb import <file>` to enable offline vulnerability matching.".to_string()],
+                });
+            }
+        };
+
+        println!("    > Matching installed versions...");
+        let installed = self.installed(wp, root)?;
+        let mut report = self.analyze(&db, &installed);
+        report.details.insert(0, format!("Database imported at {} ({} records).", db.imported_at, db.vulnerabilities.len()));
+        Ok(report)
+    }
+
+    fn fixes(&self, wp: &WpCli, root: &Path) -> Result<Vec<Box<dyn Fix>>> {
+        let db = match VulnDb::load()? {
+            Some(db) => db,
+            None => return Ok(Vec::new()),
+        };
+
+        // Active plugins with no fixed release cannot be updated out of the problem
+        let mut fixes: Vec<Box<dyn Fix>> = Vec::new();
+        for (software_type, item) in self.installed(wp, root)? {
+            if software_type != SoftwareType::Plugin || item.status != "active" {
+                continue;
+            }
+            let hits = db.find(software_type, &item.name, &item.version);
+            if let Some(vuln) = hits.iter().find(|v| v.fixed_in(&item.version).is_none()) {
+                fixes.push(Box::new(DeactivatePlugin {
+                    slug: item.name.clone(),
+                    reason: format!("{} has no fixed release", vuln.title),
+                }));
+            }
+        }
+        Ok(fixes)
+    }
+}
+
+impl VulnerabilityDiagnosis {
+    fn installed(&self, wp: &WpCli, root: &Path) -> Result<Vec<(SoftwareType, Installed)>> {
+        let mut installed = Vec::new();
+
+        let plugins: Vec<Installed> = serde_json::from_str(&wp.run(&["plugin", "list", "--format=json"], root)?)?;
+        installed.extend(plugins.into_iter().map(|p| (SoftwareType::Plugin, p)));
+
+        let themes: Vec<Installed> = serde_json::from_str(&wp.run(&["theme", "list", "--format=json"], root)?)?;
+        installed.extend(themes.into_iter().map(|t| (SoftwareType::Theme, t)));
+
+        let version = wp.run(&["core", "version"], root)?;
+        installed.push((SoftwareType::Core, Installed {
+            name: "wordpress".to_string(),
+            status: "active".to_string(),
+            version: version.trim().to_string(),
+        }));
+
+        Ok(installed)
+    }
+
+    fn analyze(&self, db: &VulnDb, installed: &[(SoftwareType, Installed)]) -> DiagnosisReport {
+        let mut details = Vec::new();
+        let mut overall_status = Status::Ok;
+        let mut found = 0;
+
+        for (software_type, item) in installed {
+            for vuln in db.find(*software_type, &item.name, &item.version) {
+                found += 1;
+                let level = if vuln.is_high_severity() { Status::Error } else { Status::Warning };
+                overall_status.escalate(level);
+                details.push(describe(*software_type, item, vuln, level));
+            }
+        }
+
+        if found == 0 {
+            details.push(format!("No known vulnerabilities in {} installed components.", installed.len()));
+        }
+
+        DiagnosisReport {
+            module: "Vulnerabilities".to_string(),
+            status: overall_status,
+            message: format!("{} known vulnerabilities found.", found),
+            details,
+        }
+    }
+}
+
+fn describe(software_type: SoftwareType, item: &Installed, vuln: &Vulnerability, level: Status) -> String {
+    let prefix = if level == Status::Error { "Critical" } else { "Warning" };
+    let kind = match software_type {
+        SoftwareType::Plugin => "Plugin",
+        SoftwareType::Theme => "Theme",
+        SoftwareType::Core => "WordPress core",
+    };
+    let cves = if vuln.cves.is_empty() { vuln.id.clone() } else { vuln.cves.join(", ") };
+    let fixed = match vuln.fixed_in(&item.version) {
+        Some(version) => format!("fixed in {}", version),
+        None => "no fix available".to_string(),
+    };
+
+    format!("{}: {} {} {} is vulnerable: {} ({}, severity {}, {}, {})",
+        prefix, kind, item.name, item.version, vuln.title, cves,
+        vuln.severity.as_deref().unwrap_or("unknown"), fixed,
+        if item.status == "active" { "active" } else { "inactive" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vulndb;
+
+    #[test]
+    fn test_analyze_reports_vulnerable_plugin() -> Result<()> {
+        let feed = r#"{"x": {
+            "id": "x", "title": "Akismet <= 5.0 - XSS",
+            "software": [{"type": "plugin", "slug": "akismet",
+                "affected_versions": {"* - 5.0": {"from_version": "*", "from_inclusive": true, "to_version": "5.0", "to_inclusive": true}},
+                "patched_versions": ["5.0.1"]}],
+            "cve": "CVE-2023-0001", "cvss": {"rating": "Medium"}
+        }}"#;
+        let db = VulnDb { source: String::new(), imported_at: String::new(), vulnerabilities: vulndb::parse(feed)? };
+        let installed = vec![
+            (SoftwareType::Plugin, Installed { name: "akismet".into(), status: "active".into(), version: "5.0".into() }),
+            (SoftwareType::Core, Installed { name: "wordpress".into(), status: "active".into(), version: "6.4.2".into() }),
+        ];
+
+        let report = VulnerabilityDiagnosis.analyze(&db, &installed);
+
+        assert_eq!(report.status, Status::Warning);
+        assert!(report.details.iter().any(|d| d.contains(
+            "Plugin akismet 5.0 is vulnerable: Akismet <= 5.0 - XSS (CVE-2023-0001, severity Medium, fixed in 5.0.1, active)")));
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+use crate::diagnosis::permissions;
+use crate::fix::{Fix, JournalEntry, Revert};
+use crate::wp::WpCli;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use anyhow::Result;
+
+/// Sets a constant in wp-config.php via `wp config set`.
+pub struct SetConfigConstant {
+    pub name: String,
+    /// Raw PHP value, e.g. `false` or `'production'`.
+    pub value: String,
+}
+
+impl SetConfigConstant {
+    /// The current value as a PHP literal, or `None` when the constant is not defined.
+    fn current(&self, wp: &WpCli, root: &Path) -> Result<Option<String>> {
+        // Plain `wp config get` prints `false` as an empty string, so read the typed JSON value
+        match wp.run(&["config", "get", &self.name, "--type=constant", "--format=json"], root) {
+            Ok(output) => php_literal(&serde_json::from_str(output.trim())?).map(Some),
+            // Anything else (unreadable config, PHP fatal) must stop the fix, or undo would delete the constant
+            Err(e) if is_not_defined(&e.to_string()) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn revert_for(&self, previous: Option<String>) -> Revert {
+        let args = match previous {
+            Some(value) => vec!["config", "set", &self.name, &value, "--raw", "--type=constant"]
+                .into_iter().map(String::from).collect(),
+            None => vec!["config", "delete", &self.name, "--type=constant"]
+                .into_iter().map(String::from).collect(),
+        };
+        Revert::WpCli { args }
+    }
+}
+
+impl Fix for SetConfigConstant {
+    fn describe(&self) -> String {
+        format!("Set {} to {} in wp-config.php", self.name, self.value)
+    }
+
+    fn preview(&self, wp: &WpCli, root: &Path) -> Result<Vec<String>> {
+        let current = self.current(wp, root)?.unwrap_or_else(|| "(not defined)".to_string());
+        Ok(vec![format!("wp-config.php: {} {} -> {}", self.name, current, self.value)])
+    }
+
+    fn apply(&self, wp: &WpCli, root: &Path) -> Result<JournalEntry> {
+        let previous = self.current(wp, root)?;
+        wp.run(&["config", "set", &self.name, &self.value, "--raw", "--type=constant"], root)?;
+
+        Ok(JournalEntry {
+            fix: self.describe(),
+            changes: vec![format!("{}: {} -> {}", self.name,
+                previous.as_deref().unwrap_or("(not defined)"), self.value)],
+            revert: vec![self.revert_for(previous)],
+        })
+    }
+}
+
+/// WP-CLI's error for a missing constant: "The constant 'X' is not defined in the 'wp-config.php' file."
+fn is_not_defined(error: &str) -> bool {
+    error.contains("is not defined in the")
+}
+
+/// Encodes a JSON value from `wp config get --format=json` as a PHP literal for `--raw`.
+fn php_literal(value: &serde_json::Value) -> Result<String> {
+    use serde_json::Value;
+    Ok(match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'")),
+        _ => return Err(anyhow::anyhow!("Unsupported constant value: {}", value)),
+    })
+}
+
+/// Deletes expired transients via `wp transient delete --expired`.
+pub struct DeleteExpiredTransients;
+
+impl Fix for DeleteExpiredTransients {
+    fn describe(&self) -> String {
+        "Delete expired transients".to_string()
+    }
+
+    fn preview(&self, wp: &WpCli, root: &Path) -> Result<Vec<String>> {
+        let prefix = wp.run(&["config", "get", "table_prefix"], root)?;
+        let query = format!(
+            "SELECT COUNT(*) FROM {}options WHERE option_name LIKE '\\_transient\\_timeout\\_%' AND option_value < UNIX_TIMESTAMP()",
+            prefix.trim()
+        );
+        let count = wp.run(&["db", "query", &query, "--skip-column-names"], root)?;
+        Ok(vec![format!("{} expired transients would be deleted", count.trim())])
+    }
+
+    fn apply(&self, wp: &WpCli, root: &Path) -> Result<JournalEntry> {
+        let output = wp.run(&["transient", "delete", "--expired"], root)?;
+
+        Ok(JournalEntry {
+            fix: self.describe(),
+            changes: vec![output.trim().to_string()],
+            revert: vec![Revert::Irreversible {
+                reason: "expired transients are regenerated by WordPress on demand".to_string(),
+            }],
+        })
+    }
+}
+
+/// Deactivates a plugin via `wp plugin deactivate`.
+pub struct DeactivatePlugin {
+    pub slug: String,
+    /// Why the plugin should be deactivated, shown in the preview.
+    pub reason: String,
+}
+
+impl Fix for DeactivatePlugin {
+    fn describe(&self) -> String {
+        format!("Deactivate plugin {}", self.slug)
+    }
+
+    fn preview(&self, _wp: &WpCli, _root: &Path) -> Result<Vec<String>> {
+        Ok(vec![format!("{} would be deactivated: {}", self.slug, self.reason)])
+    }
+
+    fn apply(&self, wp: &WpCli, root: &Path) -> Result<JournalEntry> {
+        wp.run(&["plugin", "deactivate", &self.slug], root)?;
+
+        Ok(JournalEntry {
+            fix: self.describe(),
+            changes: vec![format!("{} deactivated", self.slug)],
+            revert: vec![Revert::WpCli {
+                args: vec!["plugin".to_string(), "activate".to_string(), self.slug.clone()],
+            }],
+        })
+    }
+}
+
+/// Chmods files to 644, directories to 755 and wp-config.php to 600/640.
+pub struct NormalizePermissions {
+    /// Relative to the WordPress root.
+    pub paths: Vec<String>,
+}
+
+impl NormalizePermissions {
+    /// (path, current mode, target mode) for every path that would change.
+    fn changes(&self, root: &Path) -> Vec<(String, u32, u32)> {
+        self.paths.iter()
+            .filter_map(|path| {
+                let metadata = fs::symlink_metadata(root.join(path)).ok()?;
+                let current = metadata.permissions().mode() & 0o7777;
+                let target = permissions::target_mode(path, metadata.is_dir(), current);
+                (current != target && !metadata.file_type().is_symlink()).then(|| (path.clone(), current, target))
+            })
+            .collect()
+    }
+}
+
+impl Fix for NormalizePermissions {
+    fn describe(&self) -> String {
+        format!("Normalise permissions of {} files and directories", self.paths.len())
+    }
+
+    fn preview(&self, _wp: &WpCli, root: &Path) -> Result<Vec<String>> {
+        let changes = self.changes(root);
+        let mut lines: Vec<String> = changes.iter().take(10)
+            .map(|(path, current, target)| format!("{}: {:o} -> {:o}", path, current, target))
+            .collect();
+        if changes.len() > 10 {
+            lines.push(format!("... and {} more", changes.len() - 10));
+        }
+        lines.push("The PHP process must run as the file owner, or uploads and updates will fail afterwards.".to_string());
+        Ok(lines)
+    }
+
+    fn apply(&self, _wp: &WpCli, root: &Path) -> Result<JournalEntry> {
+        let mut changed = Vec::new();
+        let mut modes = Vec::new();
+        // Keep going past failures so the journal covers every mode that did change
+        for (path, current, target) in self.changes(root) {
+            match fs::set_permissions(root.join(&path), fs::Permissions::from_mode(target)) {
+                Ok(()) => {
+                    changed.push(format!("{}: {:o} -> {:o}", path, current, target));
+                    modes.push((path, current));
+                }
+                Err(e) => changed.push(format!("{}: chmod {:o} failed: {}", path, target, e)),
+            }
+        }
+
+        Ok(JournalEntry {
+            fix: self.describe(),
+            changes: changed,
+            revert: vec![Revert::Chmod { modes }],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_config_constant_revert() {
+        let fix = SetConfigConstant { name: "WP_DEBUG".to_string(), value: "false".to_string() };
+
+        assert_eq!(fix.revert_for(Some("true".to_string())), Revert::WpCli {
+            args: vec!["config", "set", "WP_DEBUG", "true", "--raw", "--type=constant"]
+                .into_iter().map(String::from).collect(),
+        });
+        assert_eq!(fix.revert_for(None), Revert::WpCli {
+            args: vec!["config", "delete", "WP_DEBUG", "--type=constant"]
+                .into_iter().map(String::from).collect(),
+        });
+    }
+
+    #[test]
+    fn test_php_literal_from_config_json() -> Result<()> {
+        let literal = |json: &str| php_literal(&serde_json::from_str(json)?);
+
+        assert_eq!(literal("false")?, "false");
+        assert_eq!(literal("\"production\"")?, "'production'");
+        assert_eq!(literal(r#""it's C:\\wp""#)?, r"'it\'s C:\\wp'");
+        assert_eq!(literal("30")?, "30");
+        assert!(literal("[1]").is_err());
+
+        assert!(is_not_defined("WP-CLI failed: Error: The constant 'WP_CACHE' is not defined in the 'wp-config.php' file."));
+        assert!(!is_not_defined("WP-CLI failed: Error: Strange wp-config.php file: wp-settings.php is not loaded directly."));
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_permissions_and_revert() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        fs::create_dir(root.path().join("uploads"))?;
+        fs::write(root.path().join("wp-config.php"), "<?php")?;
+        fs::set_permissions(root.path().join("uploads"), fs::Permissions::from_mode(0o777))?;
+        fs::set_permissions(root.path().join("wp-config.php"), fs::Permissions::from_mode(0o666))?;
+        let mode = |path: &str| fs::metadata(root.path().join(path)).map(|m| m.permissions().mode() & 0o777);
+
+        let fix = NormalizePermissions { paths: vec!["uploads".to_string(), "wp-config.php".to_string()] };
+        let entry = fix.apply(&WpCli::new(), root.path())?;
+
+        assert_eq!(mode("uploads")?, 0o755);
+        assert_eq!(mode("wp-config.php")?, 0o640);
+        assert_eq!(entry.changes, vec!["uploads: 777 -> 755", "wp-config.php: 666 -> 640"]);
+
+        crate::fix::apply_revert(&WpCli::new(), root.path(), &entry.revert[0])?;
+        assert_eq!(mode("uploads")?, 0o777);
+        assert_eq!(mode("wp-config.php")?, 0o666);
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use crate::cli::{self, FixArgs, UndoArgs};
+use crate::diagnosis;
+use crate::store;
+use crate::wp::WpCli;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use console::style;
+use serde::{Deserialize, Serialize};
+
+pub mod actions;
+
+/// A remediation a diagnosis module can offer.
+pub trait Fix {
+    /// One line describing the change, e.g. "Disable WP_DEBUG".
+    fn describe(&self) -> String;
+
+    /// Describes exactly what would change, without changing anything.
+    fn preview(&self, wp: &WpCli, root: &Path) -> Result<Vec<String>>;
+
+    /// Applies the change and returns how to revert it.
+    fn apply(&self, wp: &WpCli, root: &Path) -> Result<JournalEntry>;
+}
+
+/// How to revert a single change, stored in the journal.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Revert {
+    /// Run a WP-CLI command.
+    WpCli { args: Vec<String> },
+    /// Restore file modes, paths relative to the WordPress root.
+    Chmod { modes: Vec<(String, u32)> },
+    /// The change cannot be reverted.
+    Irreversible { reason: String },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JournalEntry {
+    pub fix: String,
+    pub changes: Vec<String>,
+    pub revert: Vec<Revert>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Journal {
+    pub run_id: String,
+    pub root: String,
+    pub created_at: String,
+    pub undone_at: Option<String>,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    fn path(run_id: &str) -> Result<PathBuf> {
+        Ok(store::subdir("journal")?.join(format!("{}.json", run_id)))
+    }
+
+    fn load(run_id: &str) -> Result<Journal> {
+        let path = Journal::path(run_id)?;
+        if !path.is_file() {
+            return Err(anyhow::anyhow!("No journal found for run '{}'.", run_id));
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    fn save(&self) -> Result<()> {
+        fs::write(Journal::path(&self.run_id)?, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+pub fn run(wp: &WpCli, root: &Path, args: &FixArgs, assume_yes: bool) -> Result<()> {
+    println!("\nCollecting available fixes...");
+    let mut fixes = Vec::new();
+    for module in diagnosis::all() {
+        match module.fixes(wp, root) {
+            Ok(found) => fixes.extend(found),
+            Err(e) => eprintln!("{} Could not collect fixes: {}", style("Error:").red(), e),
+        }
+    }
+
+    if fixes.is_empty() {
+        println!("\n{}", style("No fixes available.").green().bold());
+        return Ok(());
+    }
+
+    println!("\n{}", style("=== Available Fixes ===").bold().underlined());
+    for (i, fix) in fixes.iter().enumerate() {
+        println!("\n{}. {}", i + 1, style(fix.describe()).bold());
+        match fix.preview(wp, root) {
+            Ok(lines) => lines.iter().for_each(|l| println!(" - {}", l)),
+            Err(e) => println!(" - Could not preview: {}", e),
+        }
+    }
+
+    if args.dry_run {
+        println!("\n{}", style("Dry run: nothing was changed.").yellow());
+        return Ok(());
+    }
+
+    let mut journal = Journal {
+        run_id: store::unique_id(&store::subdir("journal")?, ".json"),
+        root: root.display().to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        undone_at: None,
+        entries: Vec::new(),
+    };
+    // Claim the id now so a run started in the same second picks another one
+    journal.save()?;
+
+    println!();
+    for fix in &fixes {
+        if !cli::confirm(&format!("Apply \"{}\"?", fix.describe()), assume_yes)? {
+            continue;
+        }
+        match fix.apply(wp, root) {
+            Ok(entry) => {
+                println!("{} {}", style("✔").green(), entry.fix);
+                journal.entries.push(entry);
+                // Save after every fix so the journal is accurate even if a later one crashes
+                journal.save()?;
+            }
+            Err(e) => eprintln!("{} {}: {}", style("Error:").red(), fix.describe(), e),
+        }
+    }
+
+    if journal.entries.is_empty() {
+        let _ = fs::remove_file(Journal::path(&journal.run_id)?);
+        println!("\nNo fixes applied.");
+    } else {
+        println!("\n{} Applied {} fixes. Revert them with `wp-agent undo {}`.",
+            style("✔").green(), journal.entries.len(), journal.run_id);
+    }
+    Ok(())
+}
+
+pub fn undo(wp: &WpCli, root: &Path, args: &UndoArgs, assume_yes: bool) -> Result<()> {
+    let mut journal = Journal::load(&args.run_id)?;
+    if Path::new(&journal.root) != root {
+        return Err(anyhow::anyhow!("Run {} was applied to {}, not {:?}.", journal.run_id, journal.root, root));
+    }
+    if let Some(at) = &journal.undone_at {
+        return Err(anyhow::anyhow!("Run {} was already undone at {}.", journal.run_id, at));
+    }
+
+    println!("\n{}", style("=== Undo Plan ===").bold().underlined());
+    for entry in journal.entries.iter().rev() {
+        println!("\n{}", style(&entry.fix).bold());
+        for revert in entry.revert.iter().rev() {
+            println!(" - {}", describe_revert(revert));
+        }
+    }
+
+    if !cli::confirm(&format!("Revert run {}?", journal.run_id), assume_yes)? {
+        println!("Undo cancelled.");
+        return Ok(());
+    }
+
+    let mut failures = 0;
+    for entry in journal.entries.iter().rev() {
+        for revert in entry.revert.iter().rev() {
+            if let Err(e) = apply_revert(wp, root, revert) {
+                failures += 1;
+                eprintln!("{} {}: {}", style("Error:").red(), describe_revert(revert), e);
+            }
+        }
+    }
+
+    if failures > 0 {
+        return Err(anyhow::anyhow!("{} revert steps failed. The journal was left unchanged.", failures));
+    }
+    journal.undone_at = Some(chrono::Utc::now().to_rfc3339());
+    journal.save()?;
+    println!("\n{} Run {} reverted.", style("✔").green(), journal.run_id);
+    Ok(())
+}
+
+fn describe_revert(revert: &Revert) -> String {
+    match revert {
+        Revert::WpCli { args } => format!("wp {}", args.join(" ")),
+        Revert::Chmod { modes } => format!("restore the permissions of {} files and directories", modes.len()),
+        Revert::Irreversible { reason } => format!("cannot be reverted: {}", reason),
+    }
+}
+
+fn apply_revert(wp: &WpCli, root: &Path, revert: &Revert) -> Result<()> {
+    match revert {
+        Revert::WpCli { args } => {
+            let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+            wp.run(&args, root)?;
+        }
+        Revert::Chmod { modes } => {
+            for (path, mode) in modes {
+                fs::set_permissions(root.join(path), fs::Permissions::from_mode(*mode))?;
+            }
+        }
+        Revert::Irreversible { .. } => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_round_trip() -> Result<()> {
+        let journal = Journal {
+            run_id: "20240101-000000".to_string(),
+            root: "/var/www/html".to_string(),
+            created_at: "2024-01-01T00:00:00+00:00".to_string(),
+            undone_at: None,
+            entries: vec![JournalEntry {
+                fix: "Disable WP_DEBUG".to_string(),
+                changes: vec!["WP_DEBUG: true -> false".to_string()],
+                revert: vec![Revert::WpCli { args: vec!["config".into(), "set".into(), "WP_DEBUG".into()] }],
+            }],
+        };
+
+        let json = serde_json::to_string(&journal)?;
+        assert!(json.contains(r#""type":"wp-cli""#));
+
+        let parsed: Journal = serde_json::from_str(&json)?;
+        assert_eq!(parsed.entries[0].revert, journal.entries[0].revert);
+        Ok(())
+    }
+}
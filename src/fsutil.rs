@@ -0,0 +1,57 @@
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+/// Hex-encoded SHA-256 of a file's contents.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Recursively copies `from` to `to`, keeping permissions. Symlinks are recreated, not followed.
+pub fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        let file_type = entry.file_type();
+
+        if file_type.is_dir() {
+            fs::create_dir_all(&target)?;
+            fs::set_permissions(&target, entry.metadata()?.permissions())?;
+        } else if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Hands `path` (and everything below it) to the owner of `reference`.
+/// Only does something when running as root, where restored files would otherwise belong to root.
+pub fn match_owner(path: &Path, reference: &Path) -> Result<()> {
+    let is_root = unsafe { libc::geteuid() == 0 };
+    if !is_root {
+        return Ok(());
+    }
+
+    let metadata = fs::metadata(reference)?;
+    for entry in WalkDir::new(path).follow_root_links(false) {
+        let entry = entry?;
+        std::os::unix::fs::lchown(entry.path(), Some(metadata.uid()), Some(metadata.gid()))?;
+    }
+    Ok(())
+}
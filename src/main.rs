@@ -2,19 +2,25 @@ mod cli;
 mod wp;
 mod diagnosis;
 mod report;
+mod store;
+mod cleanup;
+mod fsutil;
+mod snapshot;
+mod fix;
+mod smoke;
+mod update;
+mod core_diff;
+mod vulndb;
+mod wporg;
+mod bisect;
+mod quarantine;
 
 use clap::Parser;
-use cli::Cli;
+use cli::{Cli, Command};
 use wp::WpCli;
 use console::style;
-use diagnosis::{Diagnosis, DiagnosisReport};
-use diagnosis::database::DatabaseDiagnosis;
-use diagnosis::plugins::PluginDiagnosis;
-use diagnosis::system::SystemDiagnosis;
-use diagnosis::network::NetworkDiagnosis;
-use diagnosis::security::SecurityDiagnosis;
-use diagnosis::performance::PerformanceDiagnosis;
-use diagnosis::maintenance::MaintenanceDiagnosis;
+use diagnosis::DiagnosisReport;
+use std::path::Path;
 
 fn main() -> anyhow::Result<()> {
     let args = Cli::parse();
@@ -24,6 +30,11 @@ fn main() -> anyhow::Result<()> {
     }
 
     println!("{}", style("WP Agent starting...").bold().cyan());
+
+    // Commands that do not need a WordPress install
+    if let Some(Command::Vulndb(opts)) = &args.command {
+        return vulndb::run(opts);
+    }
     
     let mut wp = WpCli::new();
     
@@ -43,23 +54,32 @@ fn main() -> anyhow::Result<()> {
     };
     println!("{} WordPress root found at: {:?}", style("✔").green(), root);
 
-    // 3. Run Diagnoses
+    // 3. Run the requested command, or the full diagnosis by default
+    match args.command {
+        Some(Command::Cleanup(opts)) => cleanup::run(&wp, &root, &opts, args.yes),
+        Some(Command::Snapshot(opts)) => snapshot::run_snapshot(&wp, &root, &opts),
+        Some(Command::Restore(opts)) => snapshot::run_restore(&wp, &root, &opts, args.yes),
+        Some(Command::Fix(opts)) => fix::run(&wp, &root, &opts, args.yes),
+        Some(Command::Undo(opts)) => fix::undo(&wp, &root, &opts, args.yes),
+        Some(Command::Update(opts)) => update::run(&wp, &root, &opts, args.yes),
+        Some(Command::CoreDiff(opts)) => core_diff::run(&wp, &root, &opts),
+        Some(Command::Bisect(opts)) => bisect::run(&wp, &root, &opts, args.yes),
+        Some(Command::Quarantine(opts)) => quarantine::run(&wp, &root, &opts, args.yes),
+        Some(Command::Vulndb(_)) => unreachable!("handled before WordPress detection"),
+        None => {
+            run_diagnoses(&wp, &root);
+            Ok(())
+        }
+    }
+}
+
+fn run_diagnoses(wp: &WpCli, root: &Path) {
     println!("\nRunning diagnoses...");
     
-    let modules: Vec<Box<dyn Diagnosis>> = vec![
-        Box::new(DatabaseDiagnosis),
-        Box::new(PluginDiagnosis),
-        Box::new(SystemDiagnosis),
-        Box::new(NetworkDiagnosis),
-        Box::new(SecurityDiagnosis),
-        Box::new(PerformanceDiagnosis),
-        Box::new(MaintenanceDiagnosis),
-    ];
-    
     let mut reports = Vec::new();
     
-    for module in modules {
-        match module.run(&wp, &root) {
+    for module in diagnosis::all() {
+        match module.run(wp, root) {
             Ok(report) => reports.push(report),
             Err(e) => {
                 eprintln!("{} Diagnosis module failed: {}", style("Error:").red(), e);
@@ -74,8 +94,5 @@ fn main() -> anyhow::Result<()> {
         }
     }
     
-    // 4. Report
     report::display(&reports);
-    
-    Ok(())
 }
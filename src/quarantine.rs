@@ -0,0 +1,348 @@
+use crate::cli::{self, QuarantineArgs, QuarantineCommand};
+use crate::diagnosis::checksums::{self, FileIssue};
+use crate::diagnosis::malware;
+use crate::fsutil;
+use crate::store;
+use crate::wp::WpCli;
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::Result;
+use console::style;
+use serde::{Deserialize, Serialize};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const FILES_DIR: &str = "files";
+
+/// One quarantine run, stored in `~/.wp-agent/quarantine/<id>/manifest.json`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    pub id: String,
+    pub root: String,
+    pub created_at: String,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Original location, relative to the WordPress root.
+    pub path: String,
+    /// File name inside the run's `files/` directory.
+    pub stored: String,
+    pub sha256: String,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub reason: String,
+    pub restored_at: Option<String>,
+}
+
+impl Manifest {
+    fn load(dir: &Path) -> Result<Manifest> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.is_file() {
+            return Err(anyhow::anyhow!("No quarantine manifest at {:?}.", path));
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        fs::write(dir.join(MANIFEST_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+pub fn run(wp: &WpCli, root: &Path, args: &QuarantineArgs, assume_yes: bool) -> Result<()> {
+    match &args.command {
+        QuarantineCommand::Add { files } => add(wp, root, files, assume_yes),
+        QuarantineCommand::List => list(),
+        QuarantineCommand::Restore { id, files } => restore(root, id, files, assume_yes),
+    }
+}
+
+fn add(wp: &WpCli, root: &Path, files: &[String], assume_yes: bool) -> Result<()> {
+    let selected: Vec<(String, String)> = if files.is_empty() {
+        let flagged = flagged_files(wp, root);
+        if flagged.is_empty() {
+            println!("\n{}", style("No flagged files to quarantine.").green().bold());
+            return Ok(());
+        }
+        if assume_yes {
+            return Err(anyhow::anyhow!("Name the files to quarantine when using --yes."));
+        }
+        let labels: Vec<String> = flagged.iter().map(|(path, reason)| format!("{} ({})", path, reason)).collect();
+        let chosen = dialoguer::MultiSelect::new()
+            .with_prompt("Select the files to quarantine (space to toggle, enter to confirm)")
+            .items(&labels)
+            .interact()?;
+        chosen.into_iter().map(|i| flagged[i].clone()).collect()
+    } else {
+        files.iter().map(|f| (f.clone(), "selected manually".to_string())).collect()
+    };
+    if selected.is_empty() {
+        println!("Nothing selected.");
+        return Ok(());
+    }
+
+    println!("\n{}", style("=== Quarantine Plan ===").bold().underlined());
+    for (path, reason) in &selected {
+        println!(" - {} ({})", path, reason);
+    }
+    if !cli::confirm(&format!("Move {} files out of the web root?", selected.len()), assume_yes)? {
+        println!("Quarantine cancelled.");
+        return Ok(());
+    }
+
+    let base = store::subdir("quarantine")?;
+    let id = store::unique_id(&base, "");
+    let dir = base.join(&id);
+    let manifest = quarantine(root, &dir, &id, &selected)?;
+    if manifest.entries.is_empty() {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(anyhow::anyhow!("No files were quarantined."));
+    }
+    println!("\n{} Quarantined {} files in {:?}. Restore them with `wp-agent quarantine restore {}`.",
+        style("✔").green(), manifest.entries.len(), dir, id);
+    Ok(())
+}
+
+/// Files the malware scan and core checksums flag, as (path relative to root, reason).
+fn flagged_files(wp: &WpCli, root: &Path) -> Vec<(String, String)> {
+    let mut flagged: Vec<(String, String)> = Vec::new();
+    let mut push = |path: String, reason: String| match flagged.iter_mut().find(|(p, _)| *p == path) {
+        Some((_, reasons)) => { reasons.push_str(", "); reasons.push_str(&reason); }
+        None => flagged.push((path, reason)),
+    };
+
+    println!("    > Scanning wp-content...");
+    match malware::load_rules() {
+        Ok(rules) => {
+            for finding in malware::scan(&root.join("wp-content"), &rules, SystemTime::now()).findings {
+                push(format!("wp-content/{}", finding.path), finding.reason);
+            }
+        }
+        Err(e) => eprintln!("{} {}", style("Error:").red(), e),
+    }
+
+    println!("    > Verifying core checksums...");
+    if let Ok(output) = wp.run_unchecked(&["core", "verify-checksums"], root) {
+        for finding in checksums::parse_core_checksums(&String::from_utf8_lossy(&output.stderr)) {
+            if finding.issue == FileIssue::Added {
+                push(finding.file, "unexpected file in core".to_string());
+            }
+        }
+    }
+    flagged
+}
+
+/// Moves `files` (relative to `root`) into `dir`, saving the manifest after every file.
+/// `dir` must not exist yet. Files that fail are reported and skipped.
+pub fn quarantine(root: &Path, dir: &Path, id: &str, files: &[(String, String)]) -> Result<Manifest> {
+    // Never reuse a directory, its files and manifest belong to another run
+    fs::DirBuilder::new().mode(0o700).create(dir)
+        .map_err(|e| anyhow::anyhow!("Could not create quarantine directory {:?}: {}", dir, e))?;
+    let stored_dir = dir.join(FILES_DIR);
+    fs::create_dir(&stored_dir)?;
+    let mut manifest = Manifest {
+        id: id.to_string(),
+        root: root.display().to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        entries: Vec::new(),
+    };
+
+    for (i, (path, reason)) in files.iter().enumerate() {
+        let stored = i.to_string();
+        let target = stored_dir.join(&stored);
+        match move_in(root, path, &target) {
+            Ok((metadata, sha256)) => {
+                println!("{} {}", style("✔").green(), path);
+                manifest.entries.push(Entry {
+                    path: path.clone(),
+                    stored,
+                    sha256,
+                    mode: metadata.mode() & 0o7777,
+                    uid: metadata.uid(),
+                    gid: metadata.gid(),
+                    reason: reason.clone(),
+                    restored_at: None,
+                });
+                manifest.save(dir)?;
+            }
+            Err(e) => eprintln!("{} {}: {}", style("✘").red(), path, e),
+        }
+    }
+    Ok(manifest)
+}
+
+/// Moves one file into quarantine and returns its original metadata and checksum.
+/// The checksum is taken first, so a file is only moved once it can be recorded.
+fn move_in(root: &Path, path: &str, target: &Path) -> Result<(fs::Metadata, String)> {
+    let source = inside_root(root, path)?;
+    let metadata = fs::symlink_metadata(&source)?;
+    if !metadata.is_file() {
+        return Err(anyhow::anyhow!("not a regular file"));
+    }
+    let sha256 = fsutil::sha256_file(&source)?;
+    move_file(&source, target)?;
+    // Quarantined files are never served or executed, and only readable by us
+    fs::set_permissions(target, fs::Permissions::from_mode(0o600))?;
+    Ok((metadata, sha256))
+}
+
+/// Resolves `path` against `root`, refusing anything that ends up outside of it.
+fn inside_root(root: &Path, path: &str) -> Result<PathBuf> {
+    let root = root.canonicalize()?;
+    let full = root.join(path);
+    let parent = full.parent().ok_or_else(|| anyhow::anyhow!("invalid path"))?.canonicalize()?;
+    if !parent.starts_with(&root) {
+        return Err(anyhow::anyhow!("outside the WordPress root"));
+    }
+    Ok(parent.join(full.file_name().ok_or_else(|| anyhow::anyhow!("invalid path"))?))
+}
+
+/// Renames, or copies and deletes when the quarantine is on another filesystem.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    if fsutil::sha256_file(from)? != fsutil::sha256_file(to)? {
+        let _ = fs::remove_file(to);
+        return Err(anyhow::anyhow!("copy does not match the original"));
+    }
+    fs::remove_file(from)?;
+    Ok(())
+}
+
+fn list() -> Result<()> {
+    let base = store::subdir("quarantine")?;
+    let mut ids: Vec<String> = fs::read_dir(&base)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().join(MANIFEST_FILE).is_file())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    ids.sort();
+
+    if ids.is_empty() {
+        println!("\nThe quarantine is empty.");
+        return Ok(());
+    }
+    for id in ids {
+        let manifest = Manifest::load(&base.join(&id))?;
+        println!("\n{} ({})", style(&manifest.id).bold(), manifest.root);
+        for entry in &manifest.entries {
+            let state = match &entry.restored_at {
+                Some(at) => format!("restored {}", at),
+                None => "quarantined".to_string(),
+            };
+            println!(" - {} ({}, {})", entry.path, entry.reason, state);
+        }
+    }
+    Ok(())
+}
+
+fn restore(root: &Path, id: &str, files: &[String], assume_yes: bool) -> Result<()> {
+    let dir = store::subdir("quarantine")?.join(id);
+    let mut manifest = Manifest::load(&dir)?;
+    if Path::new(&manifest.root) != root {
+        return Err(anyhow::anyhow!("Quarantine {} was taken from {}, not {:?}.", id, manifest.root, root));
+    }
+
+    let pending: Vec<String> = manifest.entries.iter()
+        .filter(|e| e.restored_at.is_none() && (files.is_empty() || files.contains(&e.path)))
+        .map(|e| e.path.clone())
+        .collect();
+    if pending.is_empty() {
+        println!("\nNothing to restore.");
+        return Ok(());
+    }
+
+    println!("\n{}", style("=== Restore Plan ===").bold().underlined());
+    pending.iter().for_each(|p| println!(" - {}", p));
+    if !cli::confirm(&format!("Restore {} files?", pending.len()), assume_yes)? {
+        println!("Restore cancelled.");
+        return Ok(());
+    }
+
+    let failures = restore_entries(root, &dir, &mut manifest, &pending)?;
+    if failures > 0 {
+        return Err(anyhow::anyhow!("{} files could not be restored.", failures));
+    }
+    println!("\n{} Restored {} files.", style("✔").green(), pending.len());
+    Ok(())
+}
+
+/// Puts the named entries back with their original mode (and owner, when running as root).
+/// Returns how many failed; the manifest records the ones that succeeded.
+pub fn restore_entries(root: &Path, dir: &Path, manifest: &mut Manifest, paths: &[String]) -> Result<usize> {
+    let mut failures = 0;
+    for entry in manifest.entries.iter_mut().filter(|e| paths.contains(&e.path)) {
+        match restore_entry(root, dir, entry) {
+            Ok(()) => {
+                println!("{} {}", style("✔").green(), entry.path);
+                entry.restored_at = Some(chrono::Utc::now().to_rfc3339());
+            }
+            Err(e) => {
+                failures += 1;
+                eprintln!("{} {}: {}", style("✘").red(), entry.path, e);
+            }
+        }
+    }
+    manifest.save(dir)?;
+    Ok(failures)
+}
+
+fn restore_entry(root: &Path, dir: &Path, entry: &Entry) -> Result<()> {
+    let stored = dir.join(FILES_DIR).join(&entry.stored);
+    if fsutil::sha256_file(&stored)? != entry.sha256 {
+        return Err(anyhow::anyhow!("quarantined copy does not match its checksum"));
+    }
+    let target = inside_root(root, &entry.path)?;
+    if target.exists() {
+        return Err(anyhow::anyhow!("a file already exists at this path"));
+    }
+    move_file(&stored, &target)?;
+    fs::set_permissions(&target, fs::Permissions::from_mode(entry.mode))?;
+    if unsafe { libc::geteuid() == 0 } {
+        std::os::unix::fs::lchown(&target, Some(entry.uid), Some(entry.gid))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quarantine_and_restore() -> Result<()> {
+        let root = tempfile::tempdir()?;
+        let store = tempfile::tempdir()?;
+        let uploads = root.path().join("wp-content/uploads");
+        fs::create_dir_all(&uploads)?;
+        fs::write(uploads.join("shell.php"), "<?php eval($_POST['x']);")?;
+        fs::set_permissions(uploads.join("shell.php"), fs::Permissions::from_mode(0o640))?;
+
+        let files = vec![
+            ("wp-content/uploads/shell.php".to_string(), "PHP file in uploads".to_string()),
+            ("../outside.php".to_string(), "selected manually".to_string()),
+            ("wp-content/uploads/missing.php".to_string(), "selected manually".to_string()),
+        ];
+        let dir = store.path().join("20240101-000000");
+        let mut manifest = quarantine(root.path(), &dir, "20240101-000000", &files)?;
+
+        assert!(!uploads.join("shell.php").exists());
+        assert_eq!(manifest.entries.len(), 1);
+        assert_eq!(manifest.entries[0].mode, 0o640);
+        assert_eq!(fs::metadata(dir.join("files/0"))?.mode() & 0o777, 0o600);
+        assert_eq!(Manifest::load(&dir)?.entries, manifest.entries);
+        assert!(quarantine(root.path(), &dir, "20240101-000000", &files).is_err());
+
+        let failures = restore_entries(root.path(), &dir, &mut manifest, &["wp-content/uploads/shell.php".to_string()])?;
+        assert_eq!(failures, 0);
+        assert_eq!(fs::read_to_string(uploads.join("shell.php"))?, "<?php eval($_POST['x']);");
+        assert_eq!(fs::metadata(uploads.join("shell.php"))?.mode() & 0o777, 0o640);
+        assert!(Manifest::load(&dir)?.entries[0].restored_at.is_some());
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ pub fn display(reports: &[DiagnosisReport]) {
     println!("\n{}", style("=== WP Agent Diagnosis Report ===").bold().underlined());
     
     // Summary Table
-    println!("\n{:<20} | {:<10} | {}", "Module", "Status", "Message");
+    println!("\n{:<20} | {:<10} | Message", "Module", "Status");
     println!("{:-<20}-|-{:-<10}-|-{:-<40}", "", "", "");
     
     for report in reports {
@@ -0,0 +1,134 @@
+use crate::wp::WpCli;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::Result;
+
+/// Quick "is the site still alive" check used around risky changes.
+///
+/// Passes when the home page and wp-admin answer without a 5xx, `wp eval` runs,
+/// and no new PHP fatal errors were written to debug.log since `mark_log`.
+pub struct SmokeTest {
+    home: String,
+    debug_log: PathBuf,
+    log_offset: u64,
+    client: reqwest::blocking::Client,
+}
+
+impl SmokeTest {
+    pub fn prepare(wp: &WpCli, root: &Path) -> Result<Self> {
+        let home = wp.run(&["option", "get", "home"], root)?.trim().trim_end_matches('/').to_string();
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+
+        let mut smoke = SmokeTest {
+            home,
+            debug_log: debug_log_path(wp, root),
+            log_offset: 0,
+            client,
+        };
+        smoke.mark_log();
+        Ok(smoke)
+    }
+
+    /// Only PHP fatals logged after this point count as failures.
+    pub fn mark_log(&mut self) {
+        self.log_offset = fs::metadata(&self.debug_log).map(|m| m.len()).unwrap_or(0);
+    }
+
+    /// Runs every check and returns one line per failure. Empty means healthy.
+    pub fn run(&self, wp: &WpCli, root: &Path) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        for url in [format!("{}/", self.home), format!("{}/wp-admin/", self.home)] {
+            match self.client.get(&url).send() {
+                Ok(resp) if resp.status().is_server_error() => {
+                    failures.push(format!("{} returned {}", url, resp.status()));
+                }
+                Ok(_) => {}
+                Err(e) => failures.push(format!("{} could not be fetched: {}", url, e)),
+            }
+        }
+
+        match wp.run(&["eval", "echo 'wp-agent-ok';"], root) {
+            Ok(output) if output.contains("wp-agent-ok") => {}
+            Ok(output) => failures.push(format!("wp eval returned unexpected output: {}", output.trim())),
+            Err(e) => failures.push(format!("wp eval failed: {}", e)),
+        }
+
+        match self.read_new_log() {
+            Ok(log) => failures.extend(new_fatals(&log).into_iter().map(|l| format!("debug.log: {}", l))),
+            Err(e) => failures.push(format!("Could not read {:?}: {}", self.debug_log, e)),
+        }
+
+        failures
+    }
+
+    fn read_new_log(&self) -> Result<String> {
+        let mut file = match fs::File::open(&self.debug_log) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(String::new()),
+            Err(e) => return Err(e.into()),
+        };
+        // A truncated/rotated log is read from the start
+        if file.metadata()?.len() >= self.log_offset {
+            file.seek(SeekFrom::Start(self.log_offset))?;
+        }
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).to_string())
+    }
+}
+
+/// Resolves the debug log location, honouring a custom path in WP_DEBUG_LOG.
+fn debug_log_path(wp: &WpCli, root: &Path) -> PathBuf {
+    let value = wp.run(&["config", "get", "WP_DEBUG_LOG", "--type=constant", "--format=json"], root)
+        .ok()
+        .and_then(|output| serde_json::from_str(output.trim()).ok());
+    log_path_from(root, value)
+}
+
+/// A string value is the log path, relative ones resolved against the root like PHP does for
+/// front-end requests; `true` (or anything else) means the default location.
+fn log_path_from(root: &Path, value: Option<serde_json::Value>) -> PathBuf {
+    match value {
+        Some(serde_json::Value::String(path)) if !path.is_empty() => root.join(path),
+        _ => root.join("wp-content/debug.log"),
+    }
+}
+
+fn new_fatals(log: &str) -> Vec<String> {
+    log.lines()
+        .filter(|l| l.contains("PHP Fatal error") || l.contains("PHP Parse error"))
+        .map(|l| l.trim().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_fatals() {
+        let log = "[01-Jan-2024 10:00:00 UTC] PHP Notice:  Undefined index: foo\n\
+                   [01-Jan-2024 10:00:01 UTC] PHP Fatal error:  Uncaught Error: Call to undefined function bar()\n\
+                   [01-Jan-2024 10:00:02 UTC] PHP Parse error:  syntax error in baz.php\n";
+
+        let fatals = new_fatals(log);
+
+        assert_eq!(fatals.len(), 2);
+        assert!(fatals[0].contains("undefined function bar()"));
+    }
+
+    #[test]
+    fn test_log_path_from_config_json() {
+        let root = Path::new("/var/www");
+
+        assert_eq!(log_path_from(root, Some(serde_json::json!("/var/log/wp.log"))), PathBuf::from("/var/log/wp.log"));
+        assert_eq!(log_path_from(root, Some(serde_json::json!("logs/wp.log"))), PathBuf::from("/var/www/logs/wp.log"));
+        assert_eq!(log_path_from(root, Some(serde_json::json!(true))), root.join("wp-content/debug.log"));
+        assert_eq!(log_path_from(root, None), root.join("wp-content/debug.log"));
+    }
+}
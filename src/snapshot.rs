@@ -0,0 +1,419 @@
+use crate::cli::{self, RestoreArgs, SnapshotArgs};
+use crate::fsutil;
+use crate::store;
+use crate::wp::WpCli;
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Component, Path, PathBuf};
+use anyhow::Result;
+use console::style;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+use walkdir::WalkDir;
+
+/// Paths (relative to the WordPress root) included in a snapshot by default.
+pub const DEFAULT_PATHS: &[&str] = &[
+    "wp-config.php",
+    "wp-content/plugins",
+    "wp-content/themes",
+    "wp-content/mu-plugins",
+];
+
+const MANIFEST_FILE: &str = "manifest.json";
+const DATABASE_FILE: &str = "database.sql";
+const FILES_DIR: &str = "files";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    pub id: String,
+    pub created_at: String,
+    pub root: String,
+    /// Top-level paths that were captured, relative to the WordPress root.
+    pub paths: Vec<String>,
+    pub database: bool,
+    /// Every file in the archive (except the manifest itself) with its checksum.
+    pub files: Vec<FileEntry>,
+    /// Symlinks in the archive, restored as links rather than followed.
+    #[serde(default)]
+    pub links: Vec<LinkEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct FileEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct LinkEntry {
+    pub path: String,
+    pub target: String,
+}
+
+pub fn run_snapshot(wp: &WpCli, root: &Path, args: &SnapshotArgs) -> Result<()> {
+    let paths: Vec<String> = if args.paths.is_empty() {
+        DEFAULT_PATHS.iter().map(|p| p.to_string()).collect()
+    } else {
+        args.paths.clone()
+    };
+
+    let archive = create(wp, root, &paths, !args.no_database)?;
+    println!("\n{} Snapshot written to {:?}", style("✔").green(), archive);
+    Ok(())
+}
+
+pub fn run_restore(wp: &WpCli, root: &Path, args: &RestoreArgs, assume_yes: bool) -> Result<()> {
+    let archive = resolve(&args.snapshot)?;
+    println!("\nVerifying snapshot {:?}...", archive);
+    let (staging, manifest) = open(&archive)?;
+    println!("{} {} files verified.", style("✔").green(), manifest.files.len());
+
+    println!("\n{}", style("=== Restore Plan ===").bold().underlined());
+    println!("Snapshot {} taken at {} from {}", manifest.id, manifest.created_at, manifest.root);
+    for path in &manifest.paths {
+        println!(" - replace {}", path);
+    }
+    let restore_database = manifest.database && !args.files_only;
+    if restore_database {
+        println!(" - import the database dump (overwrites the current database)");
+    }
+
+    if !cli::confirm("Restore this snapshot? Current files will be overwritten.", assume_yes)? {
+        println!("Restore cancelled.");
+        return Ok(());
+    }
+
+    apply(wp, root, staging.path(), &manifest, restore_database)?;
+    println!("\n{} Snapshot {} restored.", style("✔").green(), manifest.id);
+    Ok(())
+}
+
+/// Takes a snapshot of `paths` (and optionally the database) and returns the archive location.
+pub fn create(wp: &WpCli, root: &Path, paths: &[String], include_database: bool) -> Result<PathBuf> {
+    let dir = store::subdir("snapshots")?;
+    let id = store::unique_id(&dir, ".tar.gz");
+    let archive = dir.join(format!("{}.tar.gz", id));
+
+    // The dump goes through a private file so large databases never sit in memory
+    let staging = tempfile::Builder::new().prefix(".export-").tempdir_in(&dir)?;
+    let dump = if include_database {
+        println!("    > Exporting database...");
+        let path = staging.path().join(DATABASE_FILE);
+        wp.run_to_file(&["db", "export", "-"], root, &path)?;
+        Some(path)
+    } else {
+        None
+    };
+
+    println!("    > Archiving files...");
+    if let Err(e) = build(&archive, &id, root, paths, dump.as_deref()) {
+        let _ = fs::remove_file(&archive);
+        return Err(e);
+    }
+    Ok(archive)
+}
+
+/// Restores a snapshot end to end, without asking. Used by commands that roll back their own changes.
+pub fn restore(wp: &WpCli, root: &Path, archive: &Path, restore_database: bool) -> Result<Manifest> {
+    let (staging, manifest) = open(archive)?;
+    apply(wp, root, staging.path(), &manifest, restore_database && manifest.database)?;
+    Ok(manifest)
+}
+
+/// Accepts either a snapshot id or a path to an archive.
+pub fn resolve(name: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(name);
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    let stored = store::subdir("snapshots")?.join(format!("{}.tar.gz", name));
+    if stored.is_file() {
+        Ok(stored)
+    } else {
+        Err(anyhow::anyhow!("Snapshot '{}' not found.", name))
+    }
+}
+
+fn build(archive: &Path, id: &str, root: &Path, paths: &[String], dump: Option<&Path>) -> Result<Manifest> {
+    let file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(archive)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder.follow_symlinks(false);
+
+    let mut manifest = Manifest {
+        id: id.to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        root: root.display().to_string(),
+        paths: Vec::new(),
+        database: dump.is_some(),
+        files: Vec::new(),
+        links: Vec::new(),
+    };
+
+    if let Some(dump) = dump {
+        manifest.files.push(FileEntry {
+            path: DATABASE_FILE.to_string(),
+            size: fs::metadata(dump)?.len(),
+            sha256: fsutil::sha256_file(dump)?,
+        });
+        builder.append_path_with_name(dump, DATABASE_FILE)?;
+    }
+
+    let mut skipped = 0;
+    for rel in paths {
+        let source = root.join(rel);
+        if !source.exists() {
+            println!("      {} not found, skipping.", rel);
+            continue;
+        }
+        manifest.paths.push(rel.clone());
+
+        for entry in WalkDir::new(&source).sort_by_file_name() {
+            let entry = entry?;
+            let name = Path::new(FILES_DIR).join(entry.path().strip_prefix(root)?);
+            let file_type = entry.file_type();
+
+            if file_type.is_dir() {
+                builder.append_dir(&name, entry.path())?;
+            } else if file_type.is_file() {
+                manifest.files.push(FileEntry {
+                    path: name.to_string_lossy().to_string(),
+                    size: entry.metadata()?.len(),
+                    sha256: fsutil::sha256_file(entry.path())?,
+                });
+                builder.append_path_with_name(entry.path(), &name)?;
+            } else if file_type.is_symlink() {
+                // Stored as a link entry (symlinks are not followed), so restoring doesn't drop them
+                manifest.links.push(LinkEntry {
+                    path: name.to_string_lossy().to_string(),
+                    target: fs::read_link(entry.path())?.to_string_lossy().to_string(),
+                });
+                builder.append_path_with_name(entry.path(), &name)?;
+            } else {
+                skipped += 1;
+            }
+        }
+    }
+    if skipped > 0 {
+        println!("      Skipped {} special files.", skipped);
+    }
+
+    append_bytes(&mut builder, MANIFEST_FILE, &serde_json::to_vec_pretty(&manifest)?)?;
+    builder.into_inner()?.finish()?;
+    Ok(manifest)
+}
+
+fn append_bytes<W: std::io::Write>(builder: &mut tar::Builder<W>, name: &str, data: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    builder.append_data(&mut header, name, data)?;
+    Ok(())
+}
+
+/// Extracts an archive to a private staging directory and verifies it against its manifest.
+fn open(archive: &Path) -> Result<(TempDir, Manifest)> {
+    let staging = tempfile::Builder::new()
+        .prefix(".restore-")
+        .tempdir_in(store::subdir("snapshots")?)?;
+
+    let mut tar = tar::Archive::new(GzDecoder::new(fs::File::open(archive)?));
+    tar.set_preserve_permissions(true);
+    tar.unpack(staging.path())?;
+
+    let manifest: Manifest = serde_json::from_slice(&fs::read(staging.path().join(MANIFEST_FILE))?)?;
+    check_paths(&manifest)?;
+    let problems = verify(staging.path(), &manifest)?;
+    if !problems.is_empty() {
+        return Err(anyhow::anyhow!("Snapshot failed verification:\n - {}", problems.join("\n - ")));
+    }
+    Ok((staging, manifest))
+}
+
+/// Rejects manifests whose paths could point outside the WordPress root once joined to it.
+fn check_paths(manifest: &Manifest) -> Result<()> {
+    let all = manifest.paths.iter()
+        .chain(manifest.files.iter().map(|f| &f.path))
+        .chain(manifest.links.iter().map(|l| &l.path));
+    for path in all {
+        let components: Vec<Component> = Path::new(path).components().collect();
+        if components.is_empty() || !components.iter().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(anyhow::anyhow!("Snapshot manifest contains an unsafe path: {:?}", path));
+        }
+    }
+    Ok(())
+}
+
+/// Compares extracted files with the manifest checksums. Returns one line per problem.
+fn verify(staging: &Path, manifest: &Manifest) -> Result<Vec<String>> {
+    let mut expected: HashMap<&str, &FileEntry> = manifest.files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let mut links: HashMap<&str, &str> = manifest.links.iter().map(|l| (l.path.as_str(), l.target.as_str())).collect();
+    let mut problems = Vec::new();
+
+    for entry in WalkDir::new(staging).sort_by_file_name() {
+        let entry = entry?;
+        let rel = entry.path().strip_prefix(staging)?.to_string_lossy().to_string();
+        if entry.file_type().is_symlink() {
+            match links.remove(rel.as_str()) {
+                Some(target) if fs::read_link(entry.path())? != Path::new(target) => {
+                    problems.push(format!("Link target mismatch: {}", rel));
+                }
+                Some(_) => {}
+                None => problems.push(format!("Unexpected link: {}", rel)),
+            }
+            continue;
+        }
+        if !entry.file_type().is_file() || rel == MANIFEST_FILE {
+            continue;
+        }
+
+        match expected.remove(rel.as_str()) {
+            Some(file) if fsutil::sha256_file(entry.path())? != file.sha256 => {
+                problems.push(format!("Checksum mismatch: {}", rel));
+            }
+            Some(_) => {}
+            None => problems.push(format!("Unexpected file: {}", rel)),
+        }
+    }
+
+    let mut missing: Vec<&str> = expected.into_keys().chain(links.into_keys()).collect();
+    missing.sort_unstable();
+    problems.extend(missing.into_iter().map(|p| format!("Missing file: {}", p)));
+    Ok(problems)
+}
+
+fn apply(wp: &WpCli, root: &Path, staging: &Path, manifest: &Manifest, restore_database: bool) -> Result<()> {
+    for rel in &manifest.paths {
+        println!("    > Restoring {}...", rel);
+        restore_path(root, &staging.join(FILES_DIR).join(rel), rel)?;
+    }
+
+    if restore_database {
+        println!("    > Importing database...");
+        wp.run_from_file(&["db", "import", "-"], root, &staging.join(DATABASE_FILE))?;
+    }
+    Ok(())
+}
+
+fn restore_path(root: &Path, source: &Path, rel: &str) -> Result<()> {
+    let target = root.join(rel);
+    let file_type = fs::symlink_metadata(source)?.file_type();
+
+    if file_type.is_dir() {
+        // Swap directories so files added after the snapshot are removed too,
+        // keeping the current copy around until the new one is fully in place.
+        let mut old = target.clone().into_os_string();
+        old.push(".wp-agent-old");
+        let old = PathBuf::from(old);
+
+        if target.exists() {
+            fs::rename(&target, &old)?;
+        }
+        if let Err(e) = fsutil::copy_tree(source, &target) {
+            let _ = fs::remove_dir_all(&target);
+            if old.exists() {
+                fs::rename(&old, &target)?;
+            }
+            return Err(e);
+        }
+        if old.exists() {
+            fs::remove_dir_all(&old)?;
+        }
+    } else {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if file_type.is_symlink() {
+            if fs::symlink_metadata(&target).is_ok() {
+                fs::remove_file(&target)?;
+            }
+            std::os::unix::fs::symlink(fs::read_link(source)?, &target)?;
+        } else {
+            fs::copy(source, &target)?;
+        }
+    }
+
+    fsutil::match_owner(&target, root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_site() -> Result<TempDir> {
+        let root = tempfile::tempdir()?;
+        fs::write(root.path().join("wp-config.php"), "<?php // config")?;
+        fs::create_dir_all(root.path().join("wp-content/plugins/hello"))?;
+        fs::write(root.path().join("wp-content/plugins/hello/hello.php"), "<?php // hello")?;
+        Ok(root)
+    }
+
+    #[test]
+    fn test_snapshot_round_trip_restores_files() -> Result<()> {
+        let root = fake_site()?;
+        let out = tempfile::tempdir()?;
+        let archive = out.path().join("snap.tar.gz");
+        let paths = vec!["wp-config.php".to_string(), "wp-content/plugins".to_string(), "missing".to_string()];
+
+        let dump = out.path().join("dump.sql");
+        fs::write(&dump, "-- dump")?;
+        std::os::unix::fs::symlink("hello", root.path().join("wp-content/plugins/hello-link"))?;
+
+        let manifest = build(&archive, "snap", root.path(), &paths, Some(&dump))?;
+        assert_eq!(manifest.paths, vec!["wp-config.php", "wp-content/plugins"]);
+        assert_eq!(manifest.files.len(), 3);
+        assert_eq!(manifest.links, vec![LinkEntry {
+            path: "files/wp-content/plugins/hello-link".to_string(),
+            target: "hello".to_string(),
+        }]);
+
+        // Modify the site after the snapshot
+        fs::write(root.path().join("wp-config.php"), "<?php // changed")?;
+        fs::write(root.path().join("wp-content/plugins/evil.php"), "<?php // new")?;
+
+        let staging = tempfile::tempdir()?;
+        tar::Archive::new(GzDecoder::new(fs::File::open(&archive)?)).unpack(staging.path())?;
+        assert!(verify(staging.path(), &manifest)?.is_empty());
+        for rel in &manifest.paths {
+            restore_path(root.path(), &staging.path().join(FILES_DIR).join(rel), rel)?;
+        }
+
+        assert_eq!(fs::read_to_string(root.path().join("wp-config.php"))?, "<?php // config");
+        assert!(root.path().join("wp-content/plugins/hello/hello.php").exists());
+        assert!(!root.path().join("wp-content/plugins/evil.php").exists());
+        assert!(!root.path().join("wp-content/plugins.wp-agent-old").exists());
+        assert_eq!(fs::read_link(root.path().join("wp-content/plugins/hello-link"))?, Path::new("hello"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_reports_tampering() -> Result<()> {
+        let root = fake_site()?;
+        let out = tempfile::tempdir()?;
+        let archive = out.path().join("snap.tar.gz");
+        let manifest = build(&archive, "snap", root.path(), &["wp-content".to_string()], None)?;
+
+        let staging = tempfile::tempdir()?;
+        tar::Archive::new(GzDecoder::new(fs::File::open(&archive)?)).unpack(staging.path())?;
+        fs::write(staging.path().join("files/wp-content/plugins/hello/hello.php"), "tampered")?;
+        fs::write(staging.path().join("files/wp-content/extra.php"), "extra")?;
+
+        let problems = verify(staging.path(), &manifest)?;
+        assert!(problems.iter().any(|p| p.contains("Checksum mismatch: files/wp-content/plugins/hello/hello.php")));
+        assert!(problems.iter().any(|p| p.contains("Unexpected file: files/wp-content/extra.php")));
+
+        assert!(check_paths(&manifest).is_ok());
+        let mut manifest = manifest;
+        for bad in ["/etc", "../outside", "wp-content/../../outside", ""] {
+            manifest.paths = vec![bad.to_string()];
+            assert!(check_paths(&manifest).is_err(), "{:?} should be rejected", bad);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use std::fs;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+/// Directory where wp-agent keeps its own state (cleanup logs, snapshots, ...).
+///
+/// Defaults to `~/.wp-agent` so nothing ends up inside the web root.
+/// Can be overridden with the `WP_AGENT_HOME` environment variable.
+pub fn data_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("WP_AGENT_HOME") {
+        return Ok(PathBuf::from(dir));
+    }
+
+    match std::env::var_os("HOME") {
+        Some(home) => Ok(PathBuf::from(home).join(".wp-agent")),
+        None => Err(anyhow::anyhow!("Could not determine home directory. Set WP_AGENT_HOME.")),
+    }
+}
+
+/// Returns `<data_dir>/<name>`, creating it if needed.
+/// Directories are private to the current user since they may hold database dumps.
+pub fn subdir(name: &str) -> Result<PathBuf> {
+    let dir = data_dir()?.join(name);
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+    Ok(dir)
+}
+
+/// Sortable identifier for a run, e.g. `20240131-154502`.
+pub fn timestamp_id() -> String {
+    chrono::Utc::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+/// A `timestamp_id` for which `<dir>/<id><suffix>` does not exist yet,
+/// with a `-2`, `-3`, ... suffix for runs started within the same second.
+pub fn unique_id(dir: &Path, suffix: &str) -> String {
+    let base = timestamp_id();
+    let mut id = base.clone();
+    let mut n = 2;
+    while dir.join(format!("{}{}", id, suffix)).exists() {
+        id = format!("{}-{}", base, n);
+        n += 1;
+    }
+    id
+}
@@ -0,0 +1,170 @@
+use crate::cli::{self, UpdateArgs};
+use crate::diagnosis::plugins::Plugin;
+use crate::smoke::SmokeTest;
+use crate::snapshot;
+use crate::wp::WpCli;
+use std::fs;
+use std::path::Path;
+use anyhow::Result;
+use console::style;
+
+#[derive(Debug)]
+enum Outcome {
+    Updated,
+    RolledBack(Vec<String>),
+    Failed(String),
+}
+
+pub fn run(wp: &WpCli, root: &Path, args: &UpdateArgs, assume_yes: bool) -> Result<()> {
+    println!("\n    > Fetching plugin list...");
+    let output = wp.run(&["plugin", "list", "--format=json"], root)?;
+    let plugins: Vec<Plugin> = serde_json::from_str(&output)?;
+    let selected = select(&plugins, &args.plugins)?;
+
+    if selected.is_empty() {
+        println!("\n{}", style("All plugins are up to date.").green().bold());
+        return Ok(());
+    }
+
+    println!("\n{}", style("=== Update Plan ===").bold().underlined());
+    for p in &selected {
+        let target = p.update_version.as_deref().filter(|v| !v.is_empty()).unwrap_or("latest");
+        println!(" - {} {} -> {}", p.name, p.version, target);
+    }
+    println!("Each plugin is backed up, updated and smoke tested on its own, and rolled back if the test fails.");
+
+    if args.dry_run {
+        println!("\n{}", style("Dry run: nothing was updated.").yellow());
+        return Ok(());
+    }
+    if !cli::confirm(&format!("Update {} plugins?", selected.len()), assume_yes)? {
+        println!("Update cancelled.");
+        return Ok(());
+    }
+
+    println!("\n    > Running baseline smoke test...");
+    let mut smoke = SmokeTest::prepare(wp, root)?;
+    let baseline = smoke.run(wp, root);
+    if !baseline.is_empty() {
+        for failure in &baseline {
+            println!(" - {}", failure);
+        }
+        return Err(anyhow::anyhow!("The site fails the smoke test before any update; fix that first."));
+    }
+
+    let mut results = Vec::new();
+    for plugin in selected {
+        println!("\n{} {}", style("Updating").bold(), plugin.name);
+        smoke.mark_log();
+        let outcome = update_one(wp, root, &mut smoke, &plugin.name);
+        results.push((plugin.name.as_str(), outcome));
+    }
+
+    println!("\n{}", style("=== Update Summary ===").bold().underlined());
+    for (name, outcome) in &results {
+        match outcome {
+            Outcome::Updated => println!(" {} {} updated", style("✔").green(), name),
+            Outcome::RolledBack(failures) => {
+                println!(" {} {} rolled back after a failed smoke test:", style("↺").yellow(), name);
+                for failure in failures {
+                    println!("     - {}", failure);
+                }
+            }
+            Outcome::Failed(e) => println!(" {} {}: {}", style("✘").red(), name, e),
+        }
+    }
+
+    if results.iter().any(|(_, o)| matches!(o, Outcome::Failed(_))) {
+        return Err(anyhow::anyhow!("Some updates need manual attention."));
+    }
+    Ok(())
+}
+
+/// Picks the plugins to update: the requested slugs, or everything with an update available.
+fn select<'a>(plugins: &'a [Plugin], requested: &[String]) -> Result<Vec<&'a Plugin>> {
+    if requested.is_empty() {
+        return Ok(plugins.iter().filter(|p| p.update == "available").collect());
+    }
+
+    let mut selected = Vec::new();
+    for slug in requested {
+        match plugins.iter().find(|p| &p.name == slug) {
+            Some(p) if p.update == "available" => selected.push(p),
+            Some(_) => println!("{} is already up to date, skipping.", slug),
+            None => return Err(anyhow::anyhow!("Plugin '{}' is not installed.", slug)),
+        }
+    }
+    Ok(selected)
+}
+
+/// Plugin location relative to the root: its directory, or the file for single-file plugins like hello.php.
+fn plugin_path(root: &Path, slug: &str) -> String {
+    let dir = format!("wp-content/plugins/{}", slug);
+    if root.join(&dir).is_dir() {
+        dir
+    } else {
+        format!("wp-content/plugins/{}.php", slug)
+    }
+}
+
+fn update_one(wp: &WpCli, root: &Path, smoke: &mut SmokeTest, slug: &str) -> Outcome {
+    println!("    > Backing up plugin files...");
+    let backup = match snapshot::create(wp, root, &[plugin_path(root, slug)], false) {
+        Ok(path) => path,
+        Err(e) => return Outcome::Failed(format!("backup failed, plugin left untouched: {}", e)),
+    };
+
+    println!("    > Running wp plugin update...");
+    let failures = match wp.run(&["plugin", "update", slug], root) {
+        Ok(_) => {
+            println!("    > Running smoke test...");
+            smoke.run(wp, root)
+        }
+        Err(e) => vec![format!("update failed: {}", e)],
+    };
+
+    // The backup is only kept when something still needs it, i.e. when a rollback failed
+    if failures.is_empty() {
+        discard(&backup);
+        return Outcome::Updated;
+    }
+
+    println!("    > Smoke test failed, restoring backup...");
+    if let Err(e) = snapshot::restore(wp, root, &backup, false) {
+        return Outcome::Failed(format!("rollback failed, restore {:?} by hand: {}", backup, e));
+    }
+    smoke.mark_log();
+    let after = smoke.run(wp, root);
+    if !after.is_empty() {
+        return Outcome::Failed(format!("rolled back, but the site still fails the smoke test: {}", after.join("; ")));
+    }
+    discard(&backup);
+    Outcome::RolledBack(failures)
+}
+
+fn discard(backup: &Path) {
+    if let Err(e) = fs::remove_file(backup) {
+        eprintln!("{} could not remove backup {:?}: {}", style("Warning:").yellow(), backup, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_plugins() -> Result<()> {
+        let plugins: Vec<Plugin> = serde_json::from_str(r#"[
+            {"name": "akismet", "status": "active", "update": "available", "version": "5.0", "update_version": "5.3"},
+            {"name": "hello", "status": "inactive", "update": "none", "version": "1.7.2", "update_version": ""}
+        ]"#)?;
+
+        let all = select(&plugins, &[])?;
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].name, "akismet");
+
+        assert!(select(&plugins, &["hello".to_string()])?.is_empty());
+        assert!(select(&plugins, &["missing".to_string()]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,385 @@
+use crate::cli::{VulndbArgs, VulndbCommand};
+use crate::store;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use anyhow::Result;
+use console::style;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const DB_FILE: &str = "vulndb.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum SoftwareType {
+    Plugin,
+    Theme,
+    Core,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VersionRange {
+    /// `None` means "every version up to `to`".
+    pub from: Option<String>,
+    pub from_inclusive: bool,
+    /// `None` means "every version from `from` on".
+    pub to: Option<String>,
+    pub to_inclusive: bool,
+}
+
+impl VersionRange {
+    pub fn contains(&self, version: &str) -> bool {
+        let above_from = match &self.from {
+            None => true,
+            Some(from) => match compare_versions(version, from) {
+                Ordering::Greater => true,
+                Ordering::Equal => self.from_inclusive,
+                Ordering::Less => false,
+            },
+        };
+        let below_to = match &self.to {
+            None => true,
+            Some(to) => match compare_versions(version, to) {
+                Ordering::Less => true,
+                Ordering::Equal => self.to_inclusive,
+                Ordering::Greater => false,
+            },
+        };
+        above_from && below_to
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Vulnerability {
+    pub id: String,
+    pub title: String,
+    pub software_type: SoftwareType,
+    /// Plugin/theme slug, or "wordpress" for core.
+    pub slug: String,
+    pub ranges: Vec<VersionRange>,
+    pub cves: Vec<String>,
+    pub severity: Option<String>,
+    /// Every release with a fix; advisories covering several branches list one per branch.
+    #[serde(default)]
+    pub fixed_versions: Vec<String>,
+}
+
+impl Vulnerability {
+    /// The first fixed release above `installed`, i.e. the one to update to.
+    pub fn fixed_in(&self, installed: &str) -> Option<&str> {
+        self.fixed_versions.iter()
+            .filter(|v| compare_versions(v, installed) == Ordering::Greater)
+            .min_by(|a, b| compare_versions(a, b))
+            .map(|v| v.as_str())
+    }
+
+    pub fn is_high_severity(&self) -> bool {
+        matches!(self.severity.as_deref().map(|s| s.to_lowercase()).as_deref(), Some("high") | Some("critical"))
+    }
+}
+
+/// Normalized database as stored in `~/.wp-agent/vulndb.json`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VulnDb {
+    pub source: String,
+    pub imported_at: String,
+    pub vulnerabilities: Vec<Vulnerability>,
+}
+
+impl VulnDb {
+    pub fn path() -> Result<PathBuf> {
+        Ok(store::subdir("vulndb")?.join(DB_FILE))
+    }
+
+    /// Loads the imported database, or `None` if nothing was imported yet.
+    pub fn load() -> Result<Option<VulnDb>> {
+        let path = VulnDb::path()?;
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&fs::read(path)?)?))
+    }
+
+    /// Every vulnerability affecting `slug` at `version`.
+    pub fn find(&self, software_type: SoftwareType, slug: &str, version: &str) -> Vec<&Vulnerability> {
+        self.vulnerabilities.iter()
+            .filter(|v| v.software_type == software_type && v.slug == slug)
+            .filter(|v| v.ranges.iter().any(|r| r.contains(version)))
+            .collect()
+    }
+}
+
+pub fn run(args: &VulndbArgs) -> Result<()> {
+    match &args.command {
+        VulndbCommand::Import { file } => {
+            let vulnerabilities = parse(&fs::read_to_string(file)?)?;
+            let db = VulnDb {
+                source: file.display().to_string(),
+                imported_at: chrono::Utc::now().to_rfc3339(),
+                vulnerabilities,
+            };
+            fs::write(VulnDb::path()?, serde_json::to_vec(&db)?)?;
+            println!("{} Imported {} vulnerability records from {:?}.", style("✔").green(), db.vulnerabilities.len(), file);
+        }
+        VulndbCommand::Status => match VulnDb::load()? {
+            Some(db) => {
+                let mut counts: HashMap<SoftwareType, usize> = HashMap::new();
+                for v in &db.vulnerabilities {
+                    *counts.entry(v.software_type).or_default() += 1;
+                }
+                println!("Vulnerability database: {:?}", VulnDb::path()?);
+                println!(" - imported from {} at {}", db.source, db.imported_at);
+                for t in [SoftwareType::Plugin, SoftwareType::Theme, SoftwareType::Core] {
+                    println!(" - {:?}: {} records", t, counts.get(&t).copied().unwrap_or(0));
+                }
+            }
+            None => println!("No vulnerability database imported. Run `wp-agent vulndb import <file>`."),
+        },
+    }
+    Ok(())
+}
+
+/// Parses a Wordfence Intelligence feed, OSV records (single, array or JSON lines),
+/// or a database previously written by `vulndb import`.
+pub fn parse(content: &str) -> Result<Vec<Vulnerability>> {
+    let value: Value = match serde_json::from_str(content) {
+        Ok(value) => value,
+        Err(e) => {
+            // JSON lines: one OSV record per line
+            let records: Result<Vec<Value>, _> = content.lines()
+                .filter(|l| !l.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect();
+            match records {
+                Ok(records) if !records.is_empty() => Value::Array(records),
+                _ => return Err(e.into()),
+            }
+        }
+    };
+
+    if let Some(list) = value.get("vulnerabilities").and_then(|v| v.as_array()) {
+        return list.iter().map(|v| Ok(serde_json::from_value(v.clone())?)).collect();
+    }
+
+    match &value {
+        Value::Array(records) => Ok(records.iter().flat_map(from_osv).collect()),
+        Value::Object(map) if map.contains_key("affected") => Ok(from_osv(&value)),
+        Value::Object(map) => Ok(map.values().flat_map(from_wordfence).collect()),
+        _ => Err(anyhow::anyhow!("Unrecognized vulnerability database format.")),
+    }
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
+fn software_type(kind: &str, slug: &str) -> SoftwareType {
+    let kind = kind.to_lowercase();
+    if kind.contains("theme") {
+        SoftwareType::Theme
+    } else if kind.contains("core") || slug == "wordpress" {
+        SoftwareType::Core
+    } else {
+        SoftwareType::Plugin
+    }
+}
+
+fn from_wordfence(record: &Value) -> Vec<Vulnerability> {
+    let cves = str_field(record, "cve").into_iter().collect::<Vec<_>>();
+    let severity = record.get("cvss").and_then(|c| str_field(c, "rating"));
+
+    let software = record.get("software").and_then(|s| s.as_array()).cloned().unwrap_or_default();
+    software.iter().filter_map(|sw| {
+        let slug = str_field(sw, "slug")?;
+        let ranges = sw.get("affected_versions")?.as_object()?.values().map(|r| {
+            let bound = |key: &str| str_field(r, key).filter(|v| v != "*");
+            VersionRange {
+                from: bound("from_version"),
+                from_inclusive: r.get("from_inclusive").and_then(|v| v.as_bool()).unwrap_or(true),
+                to: bound("to_version"),
+                to_inclusive: r.get("to_inclusive").and_then(|v| v.as_bool()).unwrap_or(true),
+            }
+        }).collect();
+        let fixed_versions = sw.get("patched_versions").and_then(|v| v.as_array())
+            .map(|v| v.iter().filter_map(|v| v.as_str()).map(String::from).collect())
+            .unwrap_or_default();
+
+        Some(Vulnerability {
+            id: str_field(record, "id").unwrap_or_default(),
+            title: str_field(record, "title").unwrap_or_default(),
+            software_type: software_type(&str_field(sw, "type").unwrap_or_default(), &slug),
+            slug,
+            ranges,
+            cves: cves.clone(),
+            severity: severity.clone(),
+            fixed_versions,
+        })
+    }).collect()
+}
+
+fn from_osv(record: &Value) -> Vec<Vulnerability> {
+    let id = str_field(record, "id").unwrap_or_default();
+    let mut cves: Vec<String> = record.get("aliases").and_then(|a| a.as_array()).map(|a| {
+        a.iter().filter_map(|v| v.as_str()).filter(|v| v.starts_with("CVE-")).map(|v| v.to_string()).collect()
+    }).unwrap_or_default();
+    if id.starts_with("CVE-") {
+        cves.insert(0, id.clone());
+    }
+    let severity = record.get("database_specific").and_then(|d| str_field(d, "severity"));
+    let title = str_field(record, "summary").unwrap_or_else(|| id.clone());
+
+    let affected = record.get("affected").and_then(|a| a.as_array()).cloned().unwrap_or_default();
+    affected.iter().filter_map(|entry| {
+        let package = entry.get("package")?;
+        let slug = str_field(package, "name")?;
+        let mut ranges = Vec::new();
+        let mut fixed_versions = Vec::new();
+
+        for range in entry.get("ranges").and_then(|r| r.as_array()).into_iter().flatten() {
+            let mut from: Option<String> = None;
+            for event in range.get("events").and_then(|e| e.as_array()).into_iter().flatten() {
+                if let Some(introduced) = str_field(event, "introduced") {
+                    from = Some(introduced).filter(|v| v != "0");
+                } else if let Some(fixed) = str_field(event, "fixed") {
+                    ranges.push(VersionRange { from: from.take(), from_inclusive: true, to: Some(fixed.clone()), to_inclusive: false });
+                    fixed_versions.push(fixed);
+                } else if let Some(last) = str_field(event, "last_affected") {
+                    ranges.push(VersionRange { from: from.take(), from_inclusive: true, to: Some(last), to_inclusive: true });
+                }
+            }
+            if let Some(from) = from {
+                ranges.push(VersionRange { from: Some(from), from_inclusive: true, to: None, to_inclusive: false });
+            }
+        }
+        // Explicit version lists are exact matches
+        for version in entry.get("versions").and_then(|v| v.as_array()).into_iter().flatten().filter_map(|v| v.as_str()) {
+            ranges.push(VersionRange { from: Some(version.to_string()), from_inclusive: true, to: Some(version.to_string()), to_inclusive: true });
+        }
+
+        Some(Vulnerability {
+            id: id.clone(),
+            title: title.clone(),
+            software_type: software_type(&str_field(package, "ecosystem").unwrap_or_default(), &slug),
+            slug,
+            ranges,
+            cves: cves.clone(),
+            severity: severity.clone(),
+            fixed_versions,
+        })
+    }).collect()
+}
+
+/// Compares WordPress-style versions ("6.4.2", "1.0-beta2") segment by segment.
+/// Missing segments count as 0, and a pre-release sorts before its release.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn split(v: &str) -> (Vec<u64>, Option<&str>) {
+        let (release, pre) = match v.trim().split_once(['-', '+']) {
+            Some((release, pre)) => (release, Some(pre)),
+            None => (v.trim(), None),
+        };
+        let numbers = release.split('.').map(|p| {
+            p.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().unwrap_or(0)
+        }).collect();
+        (numbers, pre)
+    }
+
+    let (a_nums, a_pre) = split(a);
+    let (b_nums, b_pre) = split(b);
+    for i in 0..a_nums.len().max(b_nums.len()) {
+        let ord = a_nums.get(i).unwrap_or(&0).cmp(b_nums.get(i).unwrap_or(&0));
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(a), Some(b)) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("5.3.1", "5.3.2"), Ordering::Less);
+        assert_eq!(compare_versions("5.10", "5.9.9"), Ordering::Greater);
+        assert_eq!(compare_versions("6.4", "6.4.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0-beta", "1.0"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_parse_wordfence_feed() -> Result<()> {
+        let feed = r#"{
+            "abc-123": {
+                "id": "abc-123",
+                "title": "Contact Form 7 <= 5.3.1 - Unrestricted File Upload",
+                "software": [{
+                    "type": "plugin", "slug": "contact-form-7",
+                    "affected_versions": {"* - 5.3.1": {"from_version": "*", "from_inclusive": true, "to_version": "5.3.1", "to_inclusive": true}},
+                    "patched": true, "patched_versions": ["5.3.2"]
+                }],
+                "cve": "CVE-2020-35489",
+                "cvss": {"score": 10.0, "rating": "Critical"}
+            }
+        }"#;
+
+        let db = VulnDb { source: String::new(), imported_at: String::new(), vulnerabilities: parse(feed)? };
+
+        let hits = db.find(SoftwareType::Plugin, "contact-form-7", "5.3.0");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].cves, vec!["CVE-2020-35489"]);
+        assert_eq!(hits[0].fixed_in("5.3.0"), Some("5.3.2"));
+        assert!(hits[0].is_high_severity());
+        assert!(db.find(SoftwareType::Plugin, "contact-form-7", "5.3.2").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_osv_records() -> Result<()> {
+        let osv = r#"[{
+            "id": "WPV-2024-0001",
+            "aliases": ["CVE-2024-1111"],
+            "summary": "Twenty Twenty XSS",
+            "database_specific": {"severity": "MEDIUM"},
+            "affected": [{
+                "package": {"ecosystem": "WordPress:Theme", "name": "twentytwenty"},
+                "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "2.0"}, {"fixed": "2.3"}]}]
+            }]
+        }]"#;
+
+        let vulns = parse(osv)?;
+
+        assert_eq!(vulns[0].software_type, SoftwareType::Theme);
+        assert!(vulns[0].ranges[0].contains("2.2"));
+        assert!(!vulns[0].ranges[0].contains("2.3"));
+        assert!(!vulns[0].ranges[0].contains("1.9"));
+        assert!(!vulns[0].is_high_severity());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed_in_picks_release_above_installed() -> Result<()> {
+        let osv = r#"[{
+            "id": "WPV-2024-0002",
+            "affected": [{
+                "package": {"ecosystem": "WordPress:Plugin", "name": "multi-branch"},
+                "ranges": [{"type": "ECOSYSTEM", "events": [
+                    {"introduced": "2.0"}, {"fixed": "2.3.4"}, {"introduced": "3.0"}, {"fixed": "3.1.2"}
+                ]}]
+            }]
+        }]"#;
+
+        let vulns = parse(osv)?;
+
+        assert_eq!(vulns[0].fixed_in("2.1"), Some("2.3.4"));
+        assert_eq!(vulns[0].fixed_in("3.1.0"), Some("3.1.2"));
+        assert_eq!(vulns[0].fixed_in("3.1.2"), None);
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::process::Command;
+use std::process::{Command, Output, Stdio};
 use std::path::{Path, PathBuf};
 use which::which;
 use console::style;
@@ -6,6 +6,7 @@ use std::fs;
 use std::io::copy;
 use std::os::unix::fs::PermissionsExt;
 use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::OpenOptionsExt;
 
 pub struct WpCli {
     executable_path: PathBuf,
@@ -20,7 +21,7 @@ impl WpCli {
     }
 
     pub fn is_installed(&self) -> bool {
-        if self.executable_path == PathBuf::from("wp") {
+        if self.executable_path == Path::new("wp") {
             which("wp").is_ok()
         } else {
             self.executable_path.exists()
@@ -113,6 +114,38 @@ impl WpCli {
     }
 
     pub fn run(&self, args: &[&str], cwd: &Path) -> anyhow::Result<String> {
+        let stdout = self.execute(args, cwd, None, None)?;
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    }
+
+    /// Like `run`, but streams stdout into `path` instead of memory (e.g. for `wp db export -`).
+    /// The file is opened by us, so it may live where the site owner cannot write.
+    pub fn run_to_file(&self, args: &[&str], cwd: &Path, path: &Path) -> anyhow::Result<()> {
+        let file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?;
+        self.execute(args, cwd, None, Some(file))?;
+        Ok(())
+    }
+
+    /// Like `run`, but streams `path` to the command's stdin (e.g. for `wp db import -`).
+    pub fn run_from_file(&self, args: &[&str], cwd: &Path, path: &Path) -> anyhow::Result<String> {
+        let stdout = self.execute(args, cwd, Some(fs::File::open(path)?), None)?;
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    }
+
+    /// Runs a command and returns its full output even when it exits non-zero.
+    /// For commands that report findings through the exit status, like `verify-checksums`.
+    pub fn run_unchecked(&self, args: &[&str], cwd: &Path) -> anyhow::Result<Output> {
+        Ok(self.command(args, cwd).output()?)
+    }
+
+    /// The uid WP-CLI runs as when we are root: the owner of wp-config.php, or of `root` itself.
+    pub fn site_owner(root: &Path) -> Option<u32> {
+        let config_path = root.join("wp-config.php");
+        let target_path = if config_path.exists() { config_path.as_path() } else { root };
+        std::fs::metadata(target_path).ok().map(|m| m.uid())
+    }
+
+    fn command(&self, args: &[&str], cwd: &Path) -> Command {
         let mut cmd = self.executable_path.to_string_lossy().to_string();
         let mut final_args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
 
@@ -120,11 +153,7 @@ impl WpCli {
         let is_root = unsafe { libc::geteuid() == 0 };
 
         if is_root {
-            let config_path = cwd.join("wp-config.php");
-            let target_path = if config_path.exists() { config_path.as_path() } else { cwd };
-
-            if let Ok(metadata) = std::fs::metadata(target_path) {
-                let file_uid = metadata.uid();
+            if let Some(file_uid) = WpCli::site_owner(cwd) {
                 if file_uid == 0 {
                     final_args.push("--allow-root".to_string());
                 } else {
@@ -144,10 +173,20 @@ impl WpCli {
             }
         }
 
-        let output = Command::new(&cmd)
-            .args(&final_args)
-            .current_dir(cwd)
-            .output()?;
+        let mut command = Command::new(&cmd);
+        command.args(&final_args).current_dir(cwd);
+        command
+    }
+
+    fn execute(&self, args: &[&str], cwd: &Path, stdin: Option<fs::File>, stdout: Option<fs::File>) -> anyhow::Result<Vec<u8>> {
+        let mut command = self.command(args, cwd);
+        if let Some(stdin) = stdin {
+            command.stdin(Stdio::from(stdin));
+        }
+        if let Some(stdout) = stdout {
+            command.stdout(Stdio::from(stdout));
+        }
+        let output = command.output()?;
 
         if !output.status.success() {
              let stderr = String::from_utf8_lossy(&output.stderr);
@@ -155,6 +194,6 @@ impl WpCli {
              return Err(anyhow::anyhow!("WP-CLI failed: {}", stderr));
         }
 
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        Ok(output.stdout)
     }
 }
@@ -0,0 +1,180 @@
+use crate::store;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const API_URL: &str = "https://api.wordpress.org/plugins/info/1.2/";
+
+/// Kept short so a firewalled server falls back to the cache quickly.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Cached entries younger than this are used without asking wordpress.org again.
+const CACHE_DAYS: i64 = 7;
+
+/// What wordpress.org knows about a plugin.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PluginInfo {
+    #[serde(default)]
+    pub closed: bool,
+    pub closed_date: Option<String>,
+    pub closed_reason: Option<String>,
+    /// The plugin is not hosted on wordpress.org (premium or custom).
+    #[serde(default)]
+    pub not_found: bool,
+    /// e.g. "2024-01-10 3:05pm GMT"
+    pub last_updated: Option<String>,
+    /// Highest WordPress version the author tested with.
+    pub tested: Option<String>,
+    pub fetched_at: Option<String>,
+}
+
+impl PluginInfo {
+    /// Interprets a `plugin_information` API response (including its error responses).
+    pub fn from_api(value: &Value) -> PluginInfo {
+        let text = |key: &str| value.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+        let error = text("error").unwrap_or_default();
+
+        PluginInfo {
+            closed: error == "closed" || value.get("closed").and_then(|v| v.as_bool()).unwrap_or(false),
+            closed_date: text("closed_date"),
+            closed_reason: text("reason_text"),
+            not_found: error.to_lowercase().contains("not found"),
+            last_updated: text("last_updated"),
+            tested: text("tested"),
+            fetched_at: Some(Utc::now().to_rfc3339()),
+        }
+    }
+
+    pub fn last_updated_date(&self) -> Option<NaiveDate> {
+        let date = self.last_updated.as_deref()?.get(..10)?;
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+    }
+
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.fetched_at.as_deref()
+            .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+            .is_some_and(|d| now.signed_duration_since(d) < chrono::Duration::days(CACHE_DAYS))
+    }
+}
+
+/// Plugin metadata keyed by slug, backed by `~/.wp-agent/wporg/plugins.json`.
+///
+/// The cache file can also be copied from another machine: with `WP_AGENT_OFFLINE=1`
+/// only cached entries are used and wordpress.org is never contacted. The same happens
+/// for the rest of the run after the first failed connection.
+pub struct PluginMetadata {
+    entries: BTreeMap<String, PluginInfo>,
+    offline: bool,
+    /// Built on first use and shared by all requests of the run.
+    client: Option<reqwest::blocking::Client>,
+}
+
+impl PluginMetadata {
+    fn path() -> Result<PathBuf> {
+        Ok(store::subdir("wporg")?.join("plugins.json"))
+    }
+
+    pub fn load() -> Result<PluginMetadata> {
+        let path = PluginMetadata::path()?;
+        let entries = if path.is_file() {
+            serde_json::from_slice(&fs::read(path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(PluginMetadata {
+            entries,
+            offline: std::env::var_os("WP_AGENT_OFFLINE").is_some_and(|v| v != "0"),
+            client: None,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(PluginMetadata::path()?, serde_json::to_string_pretty(&self.entries)?)?;
+        Ok(())
+    }
+
+    /// Returns metadata for `slug`, refreshing stale entries from wordpress.org unless offline.
+    /// Falls back to a stale entry when the API cannot be reached.
+    pub fn get(&mut self, slug: &str) -> Option<&PluginInfo> {
+        let fresh = self.entries.get(slug).is_some_and(|i| i.is_fresh(Utc::now()));
+        if !fresh && !self.offline {
+            match self.client().and_then(|client| fetch(client, API_URL, slug)) {
+                Ok(info) => {
+                    self.entries.insert(slug.to_string(), info);
+                }
+                Err(e) if is_unreachable(&e) => self.offline = true,
+                Err(_) => {}
+            }
+        }
+        self.entries.get(slug)
+    }
+
+    fn client(&mut self) -> Result<&reqwest::blocking::Client> {
+        if self.client.is_none() {
+            self.client = Some(reqwest::blocking::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .timeout(Duration::from_secs(10))
+                .build()?);
+        }
+        Ok(self.client.as_ref().expect("client was just built"))
+    }
+}
+
+/// Whether wordpress.org could not be reached at all, as opposed to a bad response.
+fn is_unreachable(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_connect() || e.is_timeout())
+}
+
+fn fetch(client: &reqwest::blocking::Client, url: &str, slug: &str) -> Result<PluginInfo> {
+    let response = client.get(url)
+        .query(&[
+            ("action", "plugin_information"),
+            ("request[slug]", slug),
+            ("request[fields][sections]", "0"),
+        ])
+        .send()?;
+    // Closed and unknown plugins come back as 404 with a JSON body
+    let value: Value = serde_json::from_str(&response.text()?)?;
+    Ok(PluginInfo::from_api(&value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_api_responses() -> Result<()> {
+        let ok: Value = serde_json::from_str(r#"{"name": "Akismet", "slug": "akismet", "version": "5.3", "tested": "6.4.2", "last_updated": "2023-11-07 3:19pm GMT"}"#)?;
+        let closed: Value = serde_json::from_str(r#"{"error": "closed", "name": "Old Plugin", "slug": "old-plugin", "closed_date": "2023-02-01", "reason_text": "Security Issue"}"#)?;
+        let missing: Value = serde_json::from_str(r#"{"error": "Plugin not found."}"#)?;
+
+        let info = PluginInfo::from_api(&ok);
+        assert!(!info.closed && !info.not_found);
+        assert_eq!(info.last_updated_date(), NaiveDate::from_ymd_opt(2023, 11, 7));
+        assert_eq!(info.tested.as_deref(), Some("6.4.2"));
+
+        let info = PluginInfo::from_api(&closed);
+        assert!(info.closed);
+        assert_eq!(info.closed_reason.as_deref(), Some("Security Issue"));
+
+        assert!(PluginInfo::from_api(&missing).not_found);
+        Ok(())
+    }
+
+    #[test]
+    fn test_connection_failure_is_unreachable() -> Result<()> {
+        // Bind and release a port so nothing is listening on it
+        let port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let client = reqwest::blocking::Client::builder().connect_timeout(CONNECT_TIMEOUT).build()?;
+
+        let error = fetch(&client, &format!("http://127.0.0.1:{}/", port), "akismet").unwrap_err();
+        assert!(is_unreachable(&error));
+        assert!(!is_unreachable(&anyhow::anyhow!("expected value at line 1 column 1")));
+        Ok(())
+    }
+}